@@ -0,0 +1,614 @@
+//! An interpreter for ANSI escape sequences, to show ANSI art (`.ans` files, like on the BBSes) or the colored output of tools
+//! The bytes go into an `AnsiCanvas`, which keeps the cells & the cursor, and is then drawn in the window (or turned into a `Snapshot`)
+//!
+//! ```ignore
+//! let (art, sauce) = ansi::load_ans("welcome.ans")?;
+//! art.draw(window, 0, 0, 0);
+//! ```
+//! What is understood: CP437 text (or UTF-8, see `AnsiCanvas::utf8`), CR, LF, backspace & tabs,
+//! the SGR colors (16 colors with bold & blink, 256 colors & RGB), cursor movement (`A`-`G`, `H`, `f`, `d`, `s`, `u`)
+//! and clearing (`J`, `K`). Everything else is skipped.
+//! `.ans` files may end with SAUCE metadata (see `Sauce`), which says their width & if blinking means bright backgrounds (iCE colors)
+use std::path::Path;
+
+use crate::{colors::VGA_PALETTE, u8_to_ch, Col, Snapshot, Window};
+
+// the width of ANSI art without SAUCE, since it was made for 80 column screens
+const DEFAULT_WIDTH: u32 = 80;
+// the size of a SAUCE record, & of a line of its comments
+const SAUCE_SIZE: usize = 128;
+const COMMENT_SIZE: usize = 64;
+// the end of the text in an `.ans` file, the metadata comes after
+const SUB: u8 = 0x1a;
+// the most rows & cells a canvas can have, since files & tools can send the cursor anywhere
+const MAX_ROWS: u32 = 65535;
+const MAX_CELLS: u32 = 1 << 24;
+// an unfinished sequence longer than this is garbage, so it's dropped instead of waiting for its end forever
+const MAX_PENDING: usize = 4096;
+
+// a color as the escape sequences give it, it's only turned into a `Col` when it's written, since bold & blink change it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Color {
+    Default,
+    // a color of the 256 color palette
+    Index(u8),
+    Rgb(u8, u8, u8),
+}
+// the attributes the next chars are written with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    bold: bool,
+    blink: bool,
+    reverse: bool,
+}
+const DEFAULT_STYLE: Style = Style {
+    fg: Color::Default,
+    bg: Color::Default,
+    bold: false,
+    blink: false,
+    reverse: false,
+};
+// a cell of the canvas
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+    ch: u8,
+    fg: Col,
+    bg: Col,
+}
+/// A grid of cells that escape sequences are written into, with a cursor
+/// it's as wide as you want, and either grows downwards (for art) or scrolls (for logs)
+/// it never grows past 65535 rows (or less if it's very wide), the cursor stays on the last row then
+#[derive(Clone, Debug)]
+pub struct AnsiCanvas {
+    width: u32,
+    // the amount of rows we keep, None to grow forever
+    max_rows: Option<u32>,
+    // the cells, row by row
+    cells: Vec<Cell>,
+    cursor: (u32, u32),
+    saved_cursor: (u32, u32),
+    style: Style,
+    // the start of a sequence (or of a UTF-8 char) that hasn't fully arrived yet
+    pending: Vec<u8>,
+    /// if true, the text is UTF-8 (turned into CP437, `?` if it can't be) instead of CP437, which is what tools output
+    /// Default: false
+    pub utf8: bool,
+    /// if true, blinking makes the background bright instead (it's called iCE colors, most art after 1994 uses it)
+    /// Default: false
+    pub ice_colors: bool,
+    /// the colors when no color is set (the default is light gray on black, like DOS)
+    pub default_fg: Col,
+    pub default_bg: Col,
+    /// the set of the cells that are written
+    /// Default: 0
+    pub set: u8,
+}
+impl AnsiCanvas {
+    /// creates an empty canvas that is `width` cells wide
+    /// with `max_rows`, the rows above it scroll away like in a terminal, otherwise it grows as far down as it's written to
+    pub fn new(width: u32, max_rows: Option<u32>) -> Self {
+        Self {
+            width: width.max(1),
+            max_rows: max_rows.map(|f| f.max(1)),
+            cells: Vec::new(),
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            style: DEFAULT_STYLE,
+            pending: Vec::new(),
+            utf8: false,
+            ice_colors: false,
+            default_fg: VGA_PALETTE[7],
+            default_bg: VGA_PALETTE[0],
+            set: 0,
+        }
+    }
+    /// the size of the canvas in cells (the height is the amount of rows that were written to)
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.cells.len() as u32 / self.width)
+    }
+    /// where the next char goes
+    pub fn cursor(&self) -> (u32, u32) {
+        self.cursor
+    }
+    /// returns the cp437 char & the colors at a point, None if nothing was written there
+    pub fn cell_at(&self, x: u32, y: u32) -> Option<(u8, Col, Col)> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells
+            .get(self.index(x, y)?)
+            .map(|cell| (cell.ch, cell.fg, cell.bg))
+    }
+    /// empties the canvas & resets the cursor & the colors
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.cursor = (0, 0);
+        self.style = DEFAULT_STYLE;
+    }
+    /// interprets bytes, a sequence that is cut at the end waits for the next call
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let pending = std::mem::take(&mut self.pending);
+        let mut pos = 0;
+        while pos < pending.len() {
+            let Some(len) = self.step(&pending[pos..]) else {
+                if pending.len() - pos > MAX_PENDING {
+                    pos = pending.len();
+                }
+                break;
+            };
+            pos += len;
+        }
+        self.pending = pending[pos..].to_vec();
+    }
+    /// interprets text, as UTF-8 whatever `utf8` says
+    pub fn feed_str(&mut self, text: &str) {
+        let utf8 = std::mem::replace(&mut self.utf8, true);
+        self.feed(text.as_bytes());
+        self.utf8 = utf8;
+    }
+    /// draws the canvas in the window with its top left corner at x, y, starting from its row `first_row` (to scroll through tall art)
+    /// what doesn't fit in the window is cut
+    pub fn draw(&self, window: &mut Window, x: i32, y: i32, first_row: u32) {
+        let (width, height) = self.size();
+        for row in first_row..height {
+            let cy = y + (row - first_row) as i32;
+            for column in 0..width {
+                let cx = x + column as i32;
+                let cell = self.cells[(column + row * width) as usize];
+                window.set_char_at_bin(cx, cy, cell.ch);
+                window.set_fg_at(cx, cy, cell.fg);
+                window.set_bg_at(cx, cy, cell.bg);
+                window.set_set_at(cx, cy, self.set);
+            }
+        }
+    }
+    /// the canvas as a snapshot, to draw with `Window::apply_snapshot`
+    pub fn to_snapshot(&self) -> Snapshot {
+        let (width, height) = self.size();
+        let mut snapshot = Snapshot {
+            begin: (0, 0),
+            size: (width, height),
+            fg: Vec::with_capacity(self.cells.len() * 4),
+            bg: Vec::with_capacity(self.cells.len() * 4),
+            set: vec![self.set; self.cells.len()],
+            text: Vec::with_capacity(self.cells.len()),
+        };
+        // snapshots are column by column
+        for x in 0..width {
+            for y in 0..height {
+                let cell = self.cells[(x + y * width) as usize];
+                snapshot.text.push(cell.ch);
+                snapshot
+                    .fg
+                    .extend_from_slice(&[cell.fg.0, cell.fg.1, cell.fg.2, cell.fg.3]);
+                snapshot
+                    .bg
+                    .extend_from_slice(&[cell.bg.0, cell.bg.1, cell.bg.2, cell.bg.3]);
+            }
+        }
+        snapshot
+    }
+    // handles what's at the start of the bytes, returns how many bytes it used (None if it's incomplete)
+    fn step(&mut self, bytes: &[u8]) -> Option<usize> {
+        match bytes[0] {
+            0x1b => {
+                let next = *bytes.get(1)?;
+                if next != b'[' {
+                    match next {
+                        b'7' => self.saved_cursor = self.cursor,
+                        b'8' => self.cursor = self.saved_cursor,
+                        b'c' => self.clear(),
+                        _ => {}
+                    }
+                    return Some(2);
+                }
+                // the parameters go on until a byte in @..~
+                let end = bytes
+                    .iter()
+                    .skip(2)
+                    .position(|b| (0x40..=0x7e).contains(b))?
+                    + 2;
+                let params = std::str::from_utf8(&bytes[2..end]).unwrap_or("");
+                self.csi(params, bytes[end]);
+                Some(end + 1)
+            }
+            b'\r' => {
+                self.cursor.0 = 0;
+                Some(1)
+            }
+            // files from DOS have CR LF, tools only have LF, so LF does both
+            b'\n' => {
+                self.cursor.0 = 0;
+                self.line_feed();
+                Some(1)
+            }
+            0x08 => {
+                self.cursor.0 = self.cursor.0.min(self.width - 1).saturating_sub(1);
+                Some(1)
+            }
+            b'\t' => {
+                self.cursor.0 = ((self.cursor.0 / 8 + 1) * 8).min(self.width - 1);
+                Some(1)
+            }
+            // the bell, & the end of the text of `.ans` files
+            0x07 | SUB => Some(1),
+            byte if self.utf8 && byte >= 0x80 => {
+                let len = match byte {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf7 => 4,
+                    _ => 1,
+                };
+                if bytes.len() < len {
+                    return None;
+                }
+                let ch = std::str::from_utf8(&bytes[..len])
+                    .ok()
+                    .and_then(|f| f.chars().next())
+                    .and_then(|ch| codepage_437::CP437_WINGDINGS.encode(ch))
+                    .unwrap_or(b'?');
+                self.put(ch);
+                Some(len)
+            }
+            byte => {
+                self.put(byte);
+                Some(1)
+            }
+        }
+    }
+    // writes a char at the cursor & moves it
+    fn put(&mut self, ch: u8) {
+        // the cursor waits past the last column until there is something to write
+        if self.cursor.0 >= self.width {
+            self.cursor.0 = 0;
+            self.line_feed();
+        }
+        let (fg, bg) = self.colors();
+        let (x, y) = self.cursor;
+        if let Some(cell) = self.cell_mut(x, y) {
+            *cell = Cell { ch, fg, bg };
+        }
+        self.cursor.0 += 1;
+    }
+    // moves the cursor down, scrolling if there is no room left
+    fn line_feed(&mut self) {
+        self.cursor.1 = self.cursor.1.saturating_add(1);
+        match self.max_rows {
+            Some(max_rows) if self.cursor.1 >= max_rows => {
+                self.cursor.1 = max_rows - 1;
+                let first_row = (self.width as usize).min(self.cells.len());
+                self.cells.drain(..first_row);
+            }
+            _ => self.cursor.1 = self.cursor.1.min(self.last_row()),
+        }
+    }
+    // the last row the cursor can go to
+    fn last_row(&self) -> u32 {
+        let rows = MAX_ROWS.min(MAX_CELLS / self.width).max(1);
+        self.max_rows.unwrap_or(rows).min(rows) - 1
+    }
+    // where a cell is in `cells` (whether it's there or not), None if it can't be
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (y as usize)
+            .checked_mul(self.width as usize)?
+            .checked_add(x.min(self.width - 1) as usize)
+    }
+    // a cell, the rows down to it are added if they aren't there (None if it's past the last row)
+    fn cell_mut(&mut self, x: u32, y: u32) -> Option<&mut Cell> {
+        if y > self.last_row() {
+            return None;
+        }
+        let index = self.index(x, y)?;
+        if index >= self.cells.len() {
+            let blank = self.blank();
+            let rows = y as usize + 1;
+            self.cells.resize(rows * self.width as usize, blank);
+        }
+        Some(&mut self.cells[index])
+    }
+    // an empty cell, erasing leaves these
+    fn blank(&self) -> Cell {
+        Cell {
+            ch: b' ',
+            fg: self.default_fg,
+            bg: self.colors().1,
+        }
+    }
+    // the colors of the current style
+    fn colors(&self) -> (Col, Col) {
+        let resolve = |color: Color, bright: bool, default: Col| match color {
+            Color::Default => default,
+            Color::Index(index) if index < 8 && bright => VGA_PALETTE[index as usize + 8],
+            Color::Index(index) => palette_256(index),
+            Color::Rgb(r, g, b) => (r, g, b, 255),
+        };
+        let fg = resolve(self.style.fg, self.style.bold, self.default_fg);
+        let bg = resolve(
+            self.style.bg,
+            self.style.blink && self.ice_colors,
+            self.default_bg,
+        );
+        if self.style.reverse {
+            (bg, fg)
+        } else {
+            (fg, bg)
+        }
+    }
+    // erases the cells from `start` to `end` (excluded), they are indices in `cells`
+    fn erase(&mut self, start: usize, end: usize) {
+        let blank = self.blank();
+        let end = end.min(self.cells.len());
+        for cell in self.cells.iter_mut().take(end).skip(start) {
+            *cell = blank;
+        }
+    }
+    // CSI sequences: ESC [ parameters final-byte
+    fn csi(&mut self, params: &str, last: u8) {
+        // the private modes (like showing the cursor) mean nothing here
+        if params.starts_with('?') {
+            return;
+        }
+        let numbers: Vec<u32> = params.split(';').map(|f| f.parse().unwrap_or(0)).collect();
+        // most sequences move by 1 when they don't say
+        let count = numbers[0].max(1);
+        let last_row = self.last_row();
+        let last_column = self.width - 1;
+        let (x, y) = self.cursor;
+        match last {
+            b'A' => self.cursor.1 = y.saturating_sub(count),
+            b'B' => self.cursor.1 = y.saturating_add(count).min(last_row),
+            b'C' => self.cursor.0 = x.saturating_add(count).min(last_column),
+            b'D' => self.cursor.0 = x.min(last_column).saturating_sub(count),
+            b'E' => self.cursor = (0, y.saturating_add(count).min(last_row)),
+            b'F' => self.cursor = (0, y.saturating_sub(count)),
+            b'G' => self.cursor.0 = (count - 1).min(last_column),
+            b'd' => self.cursor.1 = (count - 1).min(last_row),
+            b'H' | b'f' => {
+                let row = numbers[0].max(1) - 1;
+                let column = numbers.get(1).map_or(1, |f| (*f).max(1)) - 1;
+                self.cursor = (column.min(last_column), row.min(last_row));
+            }
+            b'J' => {
+                // past the end is fine, `erase` stops at the cells there are
+                let here = self.index(x, y).unwrap_or(usize::MAX);
+                match numbers[0] {
+                    0 => self.erase(here, usize::MAX),
+                    1 => self.erase(0, here.saturating_add(1)),
+                    // like ANSI.SYS, the cursor goes back to the top (art counts on it)
+                    _ => {
+                        self.cells.clear();
+                        self.cursor = (0, 0);
+                    }
+                }
+            }
+            b'K' => {
+                let line = self.index(0, y).unwrap_or(usize::MAX);
+                let here = self.index(x, y).unwrap_or(usize::MAX);
+                let end = line.saturating_add(self.width as usize);
+                match numbers[0] {
+                    0 => self.erase(here, end),
+                    1 => self.erase(line, here.saturating_add(1)),
+                    _ => self.erase(line, end),
+                }
+            }
+            b'm' => self.sgr(&numbers),
+            b's' => self.saved_cursor = self.cursor,
+            b'u' => self.cursor = self.saved_cursor,
+            _ => {}
+        }
+    }
+    // SGR: the colors & attributes
+    fn sgr(&mut self, numbers: &[u32]) {
+        let mut i = 0;
+        while i < numbers.len() {
+            match numbers[i] {
+                0 => self.style = DEFAULT_STYLE,
+                1 => self.style.bold = true,
+                22 => self.style.bold = false,
+                5 | 6 => self.style.blink = true,
+                25 => self.style.blink = false,
+                7 => self.style.reverse = true,
+                27 => self.style.reverse = false,
+                n @ 30..=37 => self.style.fg = Color::Index((n - 30) as u8),
+                39 => self.style.fg = Color::Default,
+                n @ 40..=47 => self.style.bg = Color::Index((n - 40) as u8),
+                49 => self.style.bg = Color::Default,
+                n @ 90..=97 => self.style.fg = Color::Index((n - 90 + 8) as u8),
+                n @ 100..=107 => self.style.bg = Color::Index((n - 100 + 8) as u8),
+                n @ (38 | 48) => {
+                    let color = match numbers.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            numbers.get(i).map(|f| Color::Index(*f as u8))
+                        }
+                        Some(2) => {
+                            i += 4;
+                            numbers
+                                .get(i - 2..=i)
+                                .map(|f| Color::Rgb(f[0] as u8, f[1] as u8, f[2] as u8))
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if n == 38 {
+                            self.style.fg = color;
+                        } else {
+                            self.style.bg = color;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+// a color of the xterm 256 color palette: the 16 VGA colors, a 6×6×6 cube & 24 grays
+fn palette_256(index: u8) -> Col {
+    match index {
+        0..=15 => VGA_PALETTE[index as usize],
+        16..=231 => {
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            let index = index - 16;
+            (
+                level(index / 36),
+                level(index / 6 % 6),
+                level(index % 6),
+                255,
+            )
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray, 255)
+        }
+    }
+}
+/// The metadata at the end of most `.ans` files (see https://www.acid.org/info/sauce/sauce.htm)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    /// CCYYMMDD
+    pub date: String,
+    /// what kind of file it is (1 is characters), & the kind of characters (1 is ANSI)
+    pub data_type: u8,
+    pub file_type: u8,
+    /// for character files, the width in chars
+    pub width: u16,
+    /// for character files, the amount of lines
+    pub height: u16,
+    /// true if blinking means bright backgrounds (see `AnsiCanvas::ice_colors`)
+    pub ice_colors: bool,
+    /// the font it was drawn with, like "IBM VGA"
+    pub font: String,
+    pub comments: Vec<String>,
+}
+impl Sauce {
+    /// reads the SAUCE record at the end of a file, and returns it with the length of what comes before it (the art)
+    pub fn parse(data: &[u8]) -> Option<(Sauce, usize)> {
+        let start = data.len().checked_sub(SAUCE_SIZE)?;
+        let record = &data[start..];
+        if &record[..7] != b"SAUCE00" {
+            return None;
+        }
+        // the strings are CP437, padded with spaces (or zeroes)
+        let text = |range: std::ops::Range<usize>| {
+            record[range]
+                .iter()
+                .map(|f| u8_to_ch(*f))
+                .collect::<String>()
+                .trim_end_matches([' ', '\0'])
+                .to_owned()
+        };
+        let u16_at = |at: usize| u16::from_le_bytes([record[at], record[at + 1]]);
+        let comment_count = record[104] as usize;
+        // the comments are right before the record, after a "COMNT" header
+        let mut comments = Vec::new();
+        let mut end = start;
+        let comments_start = start.checked_sub(5 + comment_count * COMMENT_SIZE);
+        if let Some(comments_start) = comments_start.filter(|_| comment_count > 0) {
+            if &data[comments_start..comments_start + 5] == b"COMNT" {
+                end = comments_start;
+                comments = data[comments_start + 5..start]
+                    .chunks(COMMENT_SIZE)
+                    .map(|line| {
+                        line.iter()
+                            .map(|f| u8_to_ch(*f))
+                            .collect::<String>()
+                            .trim_end_matches([' ', '\0'])
+                            .to_owned()
+                    })
+                    .collect();
+            }
+        }
+        // the art ends with SUB
+        if end > 0 && data[end - 1] == SUB {
+            end -= 1;
+        }
+        let sauce = Sauce {
+            title: text(7..42),
+            author: text(42..62),
+            group: text(62..82),
+            date: text(82..90),
+            data_type: record[94],
+            file_type: record[95],
+            width: u16_at(96),
+            height: u16_at(98),
+            ice_colors: record[105] & 1 != 0,
+            font: text(106..128),
+            comments,
+        };
+        Some((sauce, end))
+    }
+}
+/// Interprets the content of an `.ans` file, with its SAUCE metadata if it has some
+/// the canvas is as wide as SAUCE says (80 otherwise) and as tall as the art
+pub fn read_ans(data: &[u8]) -> (AnsiCanvas, Option<Sauce>) {
+    let (sauce, len) = match Sauce::parse(data) {
+        Some((sauce, len)) => (Some(sauce), len),
+        None => (None, data.len()),
+    };
+    let width = sauce
+        .as_ref()
+        .filter(|f| f.data_type == 1 && f.width > 0)
+        .map_or(DEFAULT_WIDTH, |f| f.width as u32);
+    let mut canvas = AnsiCanvas::new(width, None);
+    canvas.ice_colors = sauce.as_ref().is_some_and(|f| f.ice_colors);
+    // without SAUCE, the art still ends at the first SUB
+    let art = &data[..len];
+    let art = art
+        .iter()
+        .position(|f| *f == SUB)
+        .map_or(art, |end| &art[..end]);
+    canvas.feed(art);
+    (canvas, sauce)
+}
+/// Loads an `.ans` file (see `read_ans`)
+pub fn load_ans<P: AsRef<Path>>(path: P) -> std::io::Result<(AnsiCanvas, Option<Sauce>)> {
+    Ok(read_ans(&std::fs::read(path)?))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn colors_and_cursor_moves_are_drawn() {
+        let mut window = Window::headless(Config::default());
+        let mut canvas = AnsiCanvas::new(window.grid_size().0, None);
+        // red A, then B at row 2 column 5 (1-based), then a bold C on blue
+        canvas.feed(b"\x1b[31mA\x1b[2;5HB\x1b[1;44mC\x1b[0m");
+        assert_eq!(canvas.cursor(), (6, 1));
+        canvas.draw(&mut window, 0, 0, 0);
+        assert_eq!(window.text_at(0, 0, 1), "A");
+        assert_eq!(window.text_at(4, 1, 2), "BC");
+        assert_eq!(window.fg_at(0, 0), Some(VGA_PALETTE[1]));
+        assert_eq!(window.fg_at(4, 1), Some(VGA_PALETTE[1]));
+        // bold makes the red bright
+        assert_eq!(window.fg_at(5, 1), Some(VGA_PALETTE[9]));
+        assert_eq!(window.bg_at(5, 1), Some(VGA_PALETTE[4]));
+        assert_eq!(window.bg_at(4, 1), Some(VGA_PALETTE[0]));
+    }
+    #[test]
+    fn canvases_stay_bounded() {
+        let mut canvas = AnsiCanvas::new(80, None);
+        // the row is clamped instead of overflowing or allocating 4 billion rows
+        canvas.feed(b"\x1b[4294967295dX\x1b[4294967295;4294967295HY");
+        assert!(canvas.size().1 <= 65535);
+        assert_eq!(canvas.cell_at(0, canvas.size().1 - 1).unwrap().0, b'X');
+        // a sequence that never ends is dropped once it's too long
+        let mut endless = b"\x1b[".to_vec();
+        endless.resize(5000, b'1');
+        canvas.feed(&endless);
+        canvas.feed(b"\x1b[HQ");
+        assert_eq!(canvas.cell_at(0, 0).unwrap().0, b'Q');
+        // & very wide canvases have less rows
+        let mut wide = AnsiCanvas::new(1 << 20, None);
+        wide.feed(b"\x1b[99999dZ");
+        assert!(wide.size().1 <= 16);
+    }
+}
@@ -0,0 +1,338 @@
+//! Turning images into cells (a char with a fg & a bg), for portraits, title screens, or pictures of the game itself
+//! The result is a `Snapshot`, to draw with `Window::apply_snapshot`
+//!
+//! ```ignore
+//! let converter = AsciiConverter::new(window, 0, AsciiMode::Glyphs);
+//! let portrait = image::open("portrait.png")?;
+//! let (width, height) = converter.fitting_size(&portrait, 30, 20);
+//! window.apply_snapshot(&converter.convert(&portrait, width, height), 1, 1);
+//! ```
+//! The modes:
+//! - `HalfBlocks`: every cell is `▀`, with the top half as the fg & the bottom half as the bg, so it has two pixels per cell (it doesn't need the font)
+//! - `Shades`: every cell is ` `, `░`, `▒`, `▓` or `█`, the fg on `background`, so that the cell has the image's average color
+//! - `Glyphs`: every char of the font is tried, and the one whose shape (& the best fg & bg for it) is the closest to the image wins
+//!
+//! Notes:
+//! - `Glyphs` compares the image with the glyphs at (up to) 8×8 pixels per cell, so it takes a moment on big images, convert once & keep the snapshot
+//! - the image is drawn over `background` first, so its transparent parts have that color
+use image::{imageops::FilterType, DynamicImage, GenericImageView, RgbaImage};
+
+use crate::{snapshot::SnapshotCell, Col, Snapshot, Window};
+
+// the most pixels per cell we compare glyphs at
+const MAX_SAMPLES: u32 = 8;
+// the chars used by the modes
+const UPPER_HALF: u8 = 223;
+const SHADES: [(u8, f32); 4] = [(176, 0.25), (177, 0.5), (178, 0.75), (219, 1.)];
+
+/// How images are turned into chars
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsciiMode {
+    /// `▀` everywhere, two pixels per cell
+    HalfBlocks,
+    /// the shade chars, a single color per cell
+    Shades,
+    /// any char of the font, matched by shape
+    Glyphs,
+}
+/// Converts images into snapshots, with the glyphs of a font (see the module's docs)
+#[derive(Clone)]
+pub struct AsciiConverter {
+    pub mode: AsciiMode,
+    /// the color under the image, and the bg of the shades
+    /// Default: black
+    pub background: Col,
+    // the set the cells are written with
+    set: u8,
+    // the size of a char of the font, in pixels
+    char_size: (u32, u32),
+    // how many pixels of the image each cell is compared at (for `Glyphs`)
+    samples: (u32, u32),
+    // every char with how much it covers each sample (from 0 to 1, row by row)
+    masks: Vec<(u8, Vec<f32>)>,
+}
+impl AsciiConverter {
+    /// a converter that matches the glyphs of one of the window's fonts (the cells are written with that set)
+    pub fn new(window: &Window, set: u8, mode: AsciiMode) -> Self {
+        Self::from_font(&window.images[set as usize], set, mode)
+    }
+    /// a converter that matches the glyphs of a font image (a 16×16 CP437 grid), the cells are written with `set`
+    pub fn from_font(font: &DynamicImage, set: u8, mode: AsciiMode) -> Self {
+        let char_size = ((font.width() / 16).max(1), (font.height() / 16).max(1));
+        let samples = (char_size.0.min(MAX_SAMPLES), char_size.1.min(MAX_SAMPLES));
+        let font = font.to_rgba8();
+        // the char 0 is skipped, since it's the transparent char of `Window::blit_snapshot`
+        let masks = (1..=255)
+            .map(|glyph| (glyph, glyph_mask(&font, glyph, char_size, samples)))
+            .collect();
+        Self {
+            mode,
+            background: (0, 0, 0, 255),
+            set,
+            char_size,
+            samples,
+            masks,
+        }
+    }
+    /// the biggest size (in cells) that fits in `max_width`×`max_height` and keeps the image's proportions
+    /// (cells aren't square, so it depends on the font)
+    pub fn fitting_size(
+        &self,
+        image: &DynamicImage,
+        max_width: u32,
+        max_height: u32,
+    ) -> (u32, u32) {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return (0, 0);
+        }
+        // how many cells tall the image is for each cell it's wide
+        let ratio =
+            height as f64 * self.char_size.0 as f64 / (width as f64 * self.char_size.1 as f64);
+        let (width, height) = if max_width as f64 * ratio <= max_height as f64 {
+            (max_width, (max_width as f64 * ratio).round() as u32)
+        } else {
+            ((max_height as f64 / ratio).round() as u32, max_height)
+        };
+        (
+            width.clamp(1, max_width.max(1)),
+            height.clamp(1, max_height.max(1)),
+        )
+    }
+    /// converts an image into a snapshot of `width`×`height` cells (it's stretched to fit, see `fitting_size`)
+    pub fn convert(&self, image: &DynamicImage, width: u32, height: u32) -> Snapshot {
+        let mut snapshot = Snapshot::new(width, height);
+        if width == 0 || height == 0 {
+            return snapshot;
+        }
+        // the pixels of each cell
+        let samples = match self.mode {
+            AsciiMode::HalfBlocks => (1, 2),
+            AsciiMode::Shades => (1, 1),
+            AsciiMode::Glyphs => self.samples,
+        };
+        let pixels = self.pixels(image, width * samples.0, height * samples.1);
+        let mut cell_pixels = Vec::with_capacity((samples.0 * samples.1) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                cell_pixels.clear();
+                for sy in 0..samples.1 {
+                    for sx in 0..samples.0 {
+                        let px = x * samples.0 + sx;
+                        let py = y * samples.1 + sy;
+                        cell_pixels.push(pixels[(px + py * width * samples.0) as usize]);
+                    }
+                }
+                let (ch, fg, bg) = match self.mode {
+                    AsciiMode::HalfBlocks => (UPPER_HALF, cell_pixels[0], cell_pixels[1]),
+                    AsciiMode::Shades => self.shade(cell_pixels[0]),
+                    AsciiMode::Glyphs => self.best_glyph(&cell_pixels),
+                };
+                snapshot.set_cell(
+                    x,
+                    y,
+                    SnapshotCell {
+                        ch,
+                        set: self.set,
+                        fg: to_col(fg),
+                        bg: to_col(bg),
+                    },
+                );
+            }
+        }
+        snapshot
+    }
+    // the image resized to a size, over the background
+    fn pixels(&self, image: &DynamicImage, width: u32, height: u32) -> Vec<[f32; 3]> {
+        let resized: RgbaImage =
+            image::imageops::resize(image, width, height, FilterType::Triangle);
+        let background = [
+            self.background.0 as f32,
+            self.background.1 as f32,
+            self.background.2 as f32,
+        ];
+        resized
+            .pixels()
+            .map(|pixel| {
+                let alpha = pixel[3] as f32 / 255.;
+                std::array::from_fn(|i| pixel[i] as f32 * alpha + background[i] * (1. - alpha))
+            })
+            .collect()
+    }
+    // the shade (& its fg) that gives a cell its color on the background
+    fn shade(&self, color: [f32; 3]) -> (u8, [f32; 3], [f32; 3]) {
+        let background = [
+            self.background.0 as f32,
+            self.background.1 as f32,
+            self.background.2 as f32,
+        ];
+        // background + coverage * (fg - background) = color, with the least coverage that keeps fg a color
+        for (ch, coverage) in SHADES {
+            let fg: [f32; 3] =
+                std::array::from_fn(|i| background[i] + (color[i] - background[i]) / coverage);
+            if fg.iter().all(|f| (-0.5..=255.5).contains(f)) {
+                // a shade that would barely show is left empty
+                if fg.iter().zip(background).all(|(f, b)| (f - b).abs() < 1.) {
+                    return (b' ', fg, background);
+                }
+                return (ch, fg, background);
+            }
+        }
+        (219, color, background)
+    }
+    // the glyph that looks the most like the pixels, with the fg & bg that make it look like them the most
+    fn best_glyph(&self, pixels: &[[f32; 3]]) -> (u8, [f32; 3], [f32; 3]) {
+        let mut best = (b' ', [0.; 3], [0.; 3]);
+        let mut best_error = f32::INFINITY;
+        for (glyph, mask) in &self.masks {
+            // the average colors of the covered & uncovered parts
+            let mut fg = [0.; 3];
+            let mut bg = [0.; 3];
+            let (mut fg_weight, mut bg_weight) = (0., 0.);
+            for (pixel, coverage) in pixels.iter().zip(mask) {
+                fg = std::array::from_fn(|i| fg[i] + pixel[i] * coverage);
+                bg = std::array::from_fn(|i| bg[i] + pixel[i] * (1. - coverage));
+                fg_weight += coverage;
+                bg_weight += 1. - coverage;
+            }
+            // a glyph that is all one or the other only has one color
+            let average: [f32; 3] = std::array::from_fn(|i| (fg[i] + bg[i]) / pixels.len() as f32);
+            let fg: [f32; 3] = if fg_weight > 1e-3 {
+                fg.map(|f| f / fg_weight)
+            } else {
+                average
+            };
+            let bg: [f32; 3] = if bg_weight > 1e-3 {
+                bg.map(|f| f / bg_weight)
+            } else {
+                average
+            };
+            let mut error = 0.;
+            for (pixel, coverage) in pixels.iter().zip(mask) {
+                for ((pixel, fg), bg) in pixel.iter().zip(fg).zip(bg) {
+                    let drawn = bg + (fg - bg) * coverage;
+                    error += (pixel - drawn) * (pixel - drawn);
+                }
+            }
+            if error < best_error {
+                best_error = error;
+                best = (*glyph, fg, bg);
+            }
+        }
+        best
+    }
+}
+// how much a glyph of the font covers each sample of its cell (the average of its pixels there)
+// fonts are white on transparent, but white on black ones work too
+fn glyph_mask(font: &RgbaImage, glyph: u8, char_size: (u32, u32), samples: (u32, u32)) -> Vec<f32> {
+    let mut mask = vec![0.; (samples.0 * samples.1) as usize];
+    let mut counts = vec![0u32; mask.len()];
+    let origin = (
+        (glyph as u32 % 16) * char_size.0,
+        (glyph as u32 / 16) * char_size.1,
+    );
+    for y in 0..char_size.1 {
+        for x in 0..char_size.0 {
+            let Some(pixel) = font.get_pixel_checked(origin.0 + x, origin.1 + y) else {
+                continue;
+            };
+            let brightness = pixel[0].max(pixel[1]).max(pixel[2]) as f32 / 255.;
+            let sample =
+                (x * samples.0 / char_size.0 + y * samples.1 / char_size.1 * samples.0) as usize;
+            mask[sample] += brightness * pixel[3] as f32 / 255.;
+            counts[sample] += 1;
+        }
+    }
+    for (coverage, count) in mask.iter_mut().zip(counts) {
+        *coverage /= count.max(1) as f32;
+    }
+    mask
+}
+fn to_col(color: [f32; 3]) -> Col {
+    let channel = |f: f32| f.round().clamp(0., 255.) as u8;
+    (channel(color[0]), channel(color[1]), channel(color[2]), 255)
+}
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::Config;
+
+    fn font() -> DynamicImage {
+        Window::headless(Config::default()).images[0].clone()
+    }
+
+    #[test]
+    fn half_blocks_have_two_pixels() {
+        let converter = AsciiConverter::from_font(&font(), 1, AsciiMode::HalfBlocks);
+        let mut image = RgbaImage::from_pixel(1, 2, Rgba([255, 0, 0, 255]));
+        image.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        let snapshot = converter.convert(&DynamicImage::ImageRgba8(image), 1, 1);
+        let cell = snapshot.cell(0, 0).unwrap();
+        assert_eq!(cell.ch, UPPER_HALF);
+        assert_eq!(cell.set, 1);
+        assert_eq!(cell.fg, (255, 0, 0, 255));
+        assert_eq!(cell.bg, (0, 0, 255, 255));
+    }
+    #[test]
+    fn shades_keep_the_color() {
+        let converter = AsciiConverter::from_font(&font(), 0, AsciiMode::Shades);
+        let convert = |color| {
+            let image = RgbaImage::from_pixel(4, 4, Rgba(color));
+            converter
+                .convert(&DynamicImage::ImageRgba8(image), 2, 2)
+                .cell(1, 1)
+                .unwrap()
+        };
+        // a quarter of light gray on black
+        let cell = convert([63, 63, 63, 255]);
+        assert_eq!(
+            (cell.ch, cell.fg, cell.bg),
+            (176, (252, 252, 252, 255), (0, 0, 0, 255))
+        );
+        // a color that no shade of a brighter fg gives is a full block
+        let cell = convert([10, 200, 30, 255]);
+        assert_eq!((cell.ch, cell.fg), (219, (10, 200, 30, 255)));
+        // transparent parts are the background
+        assert_eq!(convert([255, 255, 255, 0]).ch, b' ');
+    }
+    #[test]
+    fn glyphs_are_matched_by_shape() {
+        let font = font();
+        let converter = AsciiConverter::from_font(&font, 0, AsciiMode::Glyphs);
+        let (width, height) = (font.width() / 16, font.height() / 16);
+        // the `@` of the font, in yellow on blue
+        let glyph = font.crop_imm(
+            (b'@' as u32 % 16) * width,
+            (b'@' as u32 / 16) * height,
+            width,
+            height,
+        );
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            let coverage = glyph.get_pixel(x, y)[3];
+            Rgba([coverage, coverage, 255 - coverage, 255])
+        });
+        let cell = converter
+            .convert(&DynamicImage::ImageRgba8(image), 1, 1)
+            .cell(0, 0)
+            .unwrap();
+        assert_eq!(cell.ch, b'@');
+        // the image is compared at a few samples per cell, so the colors are a bit mixed
+        assert!(cell.fg.0 > 150 && cell.fg.2 < 100, "{:?}", cell.fg);
+        assert!(cell.bg.0 < 100 && cell.bg.2 > 150, "{:?}", cell.bg);
+    }
+    #[test]
+    fn fitting_sizes_keep_the_proportions() {
+        let converter = AsciiConverter::from_font(&font(), 0, AsciiMode::Shades);
+        let (width, height) = converter.char_size;
+        // an image of 10×5 cells
+        let image = DynamicImage::new_rgba8(width * 10, height * 5);
+        assert_eq!(converter.fitting_size(&image, 20, 20), (20, 10));
+        assert_eq!(converter.fitting_size(&image, 40, 4), (8, 4));
+        assert_eq!(
+            converter.fitting_size(&DynamicImage::new_rgba8(0, 3), 20, 20),
+            (0, 0)
+        );
+    }
+}
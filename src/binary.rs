@@ -0,0 +1,72 @@
+//! What the binary file formats have in common: reading with `Input`, varints & the error of a broken file
+//! The input recordings, the grid recordings, the snapshots & the `.xp` images all use it
+/// the error of a file that isn't what it should be
+pub(crate) fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_owned())
+}
+/// writes a LEB128 varint, small numbers (which deltas mostly are) take a single byte
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+/// A cursor over the bytes of a file, every read fails (instead of panicking) if the file ends too early
+pub(crate) struct Input<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) pos: usize,
+}
+impl<'a> Input<'a> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+    pub(crate) fn bytes(&mut self, count: usize) -> std::io::Result<&'a [u8]> {
+        // the count can come from the file, so it mustn't overflow
+        let end = self.pos.checked_add(count);
+        let bytes = end
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| invalid("the file ends too early"))?;
+        self.pos += count;
+        Ok(bytes)
+    }
+    pub(crate) fn array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+    pub(crate) fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+    pub(crate) fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+    pub(crate) fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    pub(crate) fn f32(&mut self) -> std::io::Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+    pub(crate) fn f64(&mut self) -> std::io::Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+    pub(crate) fn varint(&mut self) -> std::io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("varint too long"))
+    }
+    pub(crate) fn string(&mut self) -> std::io::Result<String> {
+        let len = self.varint()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| invalid("text isn't utf-8"))
+    }
+}
@@ -0,0 +1,192 @@
+//! Recording short animated GIFs ("clips") of the game
+//! The window gives us its frames (see `Window::start_clip`), and we only keep the ones that changed,
+//! which is very little for a grid game: a player that doesn't move costs a single frame
+//! The window's frames are kept as what the grid & the instances were, not as images: they are a lot smaller,
+//! and they are only drawn (by the software renderer, see the `raster` module) when the clip is encoded
+//! Notes:
+//! - only GIF is supported, since `image` can't encode APNG
+//! - GIF delays are in hundredths of a second, so the timing is rounded to 10ms
+//! - post-processing effects aren't in the clips, since the software renderer doesn't do them
+use std::{io::Write, path::Path};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, ImageResult, RgbaImage,
+};
+
+use crate::{
+    now_ms,
+    raster::{self, Scene},
+    Window,
+};
+
+// a frame of the clip: what the window showed, or an image someone gave us
+#[derive(Clone, Debug, PartialEq)]
+enum ClipFrame {
+    Scene(Scene),
+    Image(RgbaImage),
+}
+
+/// Frames waiting to be encoded as a GIF, with the time they were shown at
+#[derive(Clone, Debug)]
+pub struct ClipRecorder {
+    // each frame, with the time (in ms) it started being shown
+    frames: Vec<(ClipFrame, f64)>,
+    // the fonts of the window the scenes come from, decoded with the first one
+    fonts: Vec<RgbaImage>,
+    // the time of the last frame we were given (even if we didn't keep it), the last frame lasts until then
+    end: f64,
+    // the time of the last frame we looked at (kept, or dropped for being the same as the last one)
+    last_sample: Option<f64>,
+    /// the minimum time (in ms) between two frames, the frames in between are dropped
+    /// Default: 50 (GIF viewers tend to slow down anything faster than that)
+    pub min_interval: f64,
+    /// how hard the encoder tries to find good colors, from 1 (slow, best) to 30 (fast)
+    /// Default: 10
+    pub speed: i32,
+}
+impl ClipRecorder {
+    /// creates an empty clip
+    pub fn new() -> Self {
+        Self {
+            frames: Vec::new(),
+            fonts: Vec::new(),
+            end: 0.,
+            last_sample: None,
+            min_interval: 50.,
+            speed: 10,
+        }
+    }
+    /// returns true if a frame given at this time (in ms) would be kept, so that you don't make images for nothing
+    pub fn wants_frame_at(&self, time: f64) -> bool {
+        !self
+            .last_sample
+            .is_some_and(|last| time - last < self.min_interval)
+    }
+    /// adds a frame, timed with the real clock
+    pub fn add_frame(&mut self, image: RgbaImage) {
+        self.add_frame_at(image, now_ms());
+    }
+    /// adds a frame shown at this time (in ms, it can start at anything but must go up)
+    /// it's dropped if it's too soon after the last one, or the same as the last one
+    pub fn add_frame_at(&mut self, image: RgbaImage, time: f64) {
+        self.push(time, || ClipFrame::Image(image));
+    }
+    /// adds what the window shows, timed with the real clock
+    pub fn add_window_frame(&mut self, window: &Window) {
+        self.add_window_frame_at(window, now_ms());
+    }
+    /// adds what the window shows at this time (in ms), like `add_frame_at`
+    /// only the grid, the instances & the image layers are kept, the frame is drawn when the clip is encoded
+    pub fn add_window_frame_at(&mut self, window: &Window, time: f64) {
+        if self.fonts.is_empty() && self.wants_frame_at(time) {
+            self.fonts = raster::fonts(window);
+        }
+        self.push(time, || ClipFrame::Scene(Scene::capture(window)));
+    }
+    // keeps a frame if it's time for one & it's not the same as the last one (it's only made if it's time)
+    fn push(&mut self, time: f64, frame: impl FnOnce() -> ClipFrame) {
+        self.end = time;
+        if !self.wants_frame_at(time) {
+            return;
+        }
+        self.last_sample = Some(time);
+        let frame = frame();
+        if self.frames.last().is_some_and(|f| f.0 == frame) {
+            return;
+        }
+        self.frames.push((frame, time));
+    }
+    /// the amount of frames we kept
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+    /// the length of the clip, in ms
+    pub fn duration(&self) -> f64 {
+        self.frames.first().map_or(0., |f| self.end - f.1)
+    }
+    /// encodes the clip as a looping GIF (the window's frames are drawn then, one at a time)
+    pub fn write_gif<W: Write>(&self, writer: W) -> ImageResult<()> {
+        let mut encoder = GifEncoder::new_with_speed(writer, self.speed);
+        encoder.set_repeat(Repeat::Infinite)?;
+        for (i, (frame, start)) in self.frames.iter().enumerate() {
+            let end = self.frames.get(i + 1).map_or(self.end, |f| f.1);
+            // the last frame would last 0ms if it's the one we got last
+            let duration = (end - start).max(self.min_interval).round() as u32;
+            let image = match frame {
+                ClipFrame::Scene(scene) => scene.draw(&self.fonts),
+                ClipFrame::Image(image) => image.clone(),
+            };
+            encoder.encode_frame(Frame::from_parts(
+                image,
+                0,
+                0,
+                Delay::from_numer_denom_ms(duration, 1),
+            ))?;
+        }
+        Ok(())
+    }
+    /// saves the clip as a GIF file
+    pub fn save_gif<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        // the encoder is dropped (& writes the end of the GIF) before the flush
+        self.write_gif(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+impl Default for ClipRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use image::{codecs::gif::GifDecoder, AnimationDecoder, Rgba};
+
+    use super::*;
+    use crate::Config;
+
+    fn solid(value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(2, 2, Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn unchanged_and_early_frames_are_dropped() {
+        let mut window = Window::headless(Config::default());
+        let mut clip = ClipRecorder::new();
+        clip.add_window_frame_at(&window, 1000.);
+        // too soon, even though it changed
+        window.set_char_at(0, 0, '@');
+        assert!(!clip.wants_frame_at(1020.));
+        clip.add_window_frame_at(&window, 1020.);
+        assert_eq!(clip.frame_count(), 1);
+        clip.add_window_frame_at(&window, 1060.);
+        assert_eq!(clip.frame_count(), 2);
+        // the same as the last one
+        clip.add_window_frame_at(&window, 1200.);
+        assert_eq!(clip.frame_count(), 2);
+        // the last frame lasts until the last time we were given
+        assert_eq!(clip.duration(), 200.);
+    }
+    #[test]
+    fn frames_last_until_the_next_one() {
+        let mut clip = ClipRecorder::new();
+        clip.add_frame_at(solid(0), 0.);
+        clip.add_frame_at(solid(100), 120.);
+        clip.add_frame_at(solid(100), 200.);
+        clip.add_frame_at(solid(200), 400.);
+        assert_eq!(clip.frame_count(), 3);
+        let mut gif = Vec::new();
+        clip.write_gif(&mut gif).unwrap();
+        let frames = GifDecoder::new(std::io::Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        let delays: Vec<(u32, u32)> = frames.iter().map(|f| f.delay().numer_denom_ms()).collect();
+        // nothing came after the last frame, so it lasts `min_interval`
+        assert_eq!(delays, vec![(120, 1), (280, 1), (50, 1)]);
+        assert_eq!(frames[1].buffer().get_pixel(0, 0)[0], 100);
+    }
+}
@@ -8,3 +8,23 @@ pub const RED: Col = (255, 0, 0, 255);
 pub const CYAN: Col = (0, 255, 255, 255);
 pub const GREEN: Col = (255 - 255, 0 + 255, 0, 255);
 pub const TRANSPARENT: Col = (255 - 255, 0, 0, 0);
+/// the 16 VGA colors, in the order of the ANSI color codes: black, red, green, brown, blue, magenta, cyan, light gray, then their bright versions
+/// it's what CP437 art was made for
+pub const VGA_PALETTE: [Col; 16] = [
+    (0, 0, 0, 255),
+    (170, 0, 0, 255),
+    (0, 170, 0, 255),
+    (170, 85, 0, 255),
+    (0, 0, 170, 255),
+    (170, 0, 170, 255),
+    (0, 170, 170, 255),
+    (170, 170, 170, 255),
+    (85, 85, 85, 255),
+    (255, 85, 85, 255),
+    (85, 255, 85, 255),
+    (255, 255, 85, 255),
+    (85, 85, 255, 255),
+    (255, 85, 255, 255),
+    (85, 255, 255, 255),
+    (255, 255, 255, 255),
+];
@@ -0,0 +1,301 @@
+//! Exporting the grid as HTML or as plain text, for "morgue files": what the screen looked like when the character died, with the message log
+//! The HTML is a single file (no scripts, no images) with a colored span for each run of cells with the same colors,
+//! the text is UTF-8 with CP437 decoded by `u8_to_ch`
+//!
+//! ```ignore
+//! let mut morgue = Morgue::new("Rodney, killed by a newt on level 3");
+//! morgue.add_window(window, None);
+//! morgue.add_text("Last messages", log.iter().rev().take(20));
+//! morgue.save_html("morgue.html")?;
+//! morgue.save_text("morgue.txt")?;
+//! ```
+//! Notes:
+//! - a region is (x, y, width, height) in cells, like `Window::take_snapshot`, and is cut to what's there
+//! - the sets aren't exported, every cell is drawn with the browser's font
+//! - control chars (which CP437 fonts draw as symbols) become spaces
+use std::{fmt::Write as _, path::Path};
+
+use crate::{colors::BLACK, u8_to_ch, Col, Snapshot, Window};
+
+/// Turns a snapshot (or a region of it) into an HTML page
+pub fn snapshot_to_html(snapshot: &Snapshot, region: Option<(u32, u32, u32, u32)>) -> String {
+    let mut morgue = Morgue::new("");
+    morgue.add_screen(crop(snapshot, region));
+    morgue.to_html()
+}
+/// Turns a snapshot (or a region of it) into text, a line per row
+pub fn snapshot_to_text(snapshot: &Snapshot, region: Option<(u32, u32, u32, u32)>) -> String {
+    screen_text(&crop(snapshot, region))
+}
+/// Turns the grid of a window (or a region of it) into an HTML page, on the window's background color
+pub fn window_to_html(window: &Window, region: Option<(u32, u32, u32, u32)>) -> String {
+    let mut morgue = Morgue::new("");
+    morgue.background = window.background_color;
+    morgue.add_window(window, region);
+    morgue.to_html()
+}
+/// Turns the grid of a window (or a region of it) into text, a line per row
+pub fn window_to_text(window: &Window, region: Option<(u32, u32, u32, u32)>) -> String {
+    screen_text(&grid_region(window, region))
+}
+// a part of a morgue file
+#[derive(Clone)]
+enum Section {
+    Screen(Snapshot),
+    Text { heading: String, lines: Vec<String> },
+}
+/// A morgue file: a title followed by screens & text, in the order they were added
+#[derive(Clone)]
+pub struct Morgue {
+    pub title: String,
+    /// the color of the page, which shows through the transparent cells
+    /// Default: black
+    pub background: Col,
+    sections: Vec<Section>,
+}
+impl Morgue {
+    /// creates an empty morgue file (an empty title isn't shown)
+    pub fn new<S: Into<String>>(title: S) -> Self {
+        Self {
+            title: title.into(),
+            background: BLACK,
+            sections: Vec::new(),
+        }
+    }
+    /// adds a screen
+    pub fn add_screen(&mut self, snapshot: Snapshot) {
+        self.sections.push(Section::Screen(snapshot));
+    }
+    /// adds the grid of a window (or a region of it) as it is now
+    pub fn add_window(&mut self, window: &Window, region: Option<(u32, u32, u32, u32)>) {
+        self.add_screen(grid_region(window, region));
+    }
+    /// adds lines of text, like the message log, under a heading (an empty heading isn't shown)
+    pub fn add_text<H, I, S>(&mut self, heading: H, lines: I)
+    where
+        H: Into<String>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.sections.push(Section::Text {
+            heading: heading.into(),
+            lines: lines.into_iter().map(Into::into).collect(),
+        });
+    }
+    /// the morgue file as an HTML page
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(html, "<title>{}</title>", escape(&self.title));
+        let _ = writeln!(
+            html,
+            "<style>body{{background:{};color:#aaaaaa;font-family:monospace}}\
+             pre{{line-height:1;font-family:\"DejaVu Sans Mono\",Consolas,monospace}}</style>",
+            hex(self.background)
+        );
+        html.push_str("</head>\n<body>\n");
+        if !self.title.is_empty() {
+            let _ = writeln!(html, "<h1>{}</h1>", escape(&self.title));
+        }
+        for section in &self.sections {
+            match section {
+                Section::Screen(snapshot) => screen_html(snapshot, &mut html),
+                Section::Text { heading, lines } => {
+                    if !heading.is_empty() {
+                        let _ = writeln!(html, "<h2>{}</h2>", escape(heading));
+                    }
+                    html.push_str("<pre>");
+                    for line in lines {
+                        html.push_str(&escape(line));
+                        html.push('\n');
+                    }
+                    html.push_str("</pre>\n");
+                }
+            }
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+    /// the morgue file as text
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if !self.title.is_empty() {
+            text.push_str(&self.title);
+            text.push_str("\n\n");
+        }
+        for section in &self.sections {
+            match section {
+                Section::Screen(snapshot) => text.push_str(&screen_text(snapshot)),
+                Section::Text { heading, lines } => {
+                    if !heading.is_empty() {
+                        text.push_str(heading);
+                        text.push('\n');
+                    }
+                    for line in lines {
+                        text.push_str(line);
+                        text.push('\n');
+                    }
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+    /// saves the morgue file as an HTML page
+    pub fn save_html<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_html())
+    }
+    /// saves the morgue file as text
+    pub fn save_text<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+}
+// a cell of a snapshot as its char & colors
+fn cell(snapshot: &Snapshot, x: u32, y: u32) -> (char, Col, Col) {
+    let index = (y + x * snapshot.size.1) as usize;
+    let fg = &snapshot.fg[index * 4..index * 4 + 4];
+    let bg = &snapshot.bg[index * 4..index * 4 + 4];
+    let ch = u8_to_ch(snapshot.text[index]);
+    (
+        if ch.is_control() { ' ' } else { ch },
+        (fg[0], fg[1], fg[2], fg[3]),
+        (bg[0], bg[1], bg[2], bg[3]),
+    )
+}
+// a snapshot as a `<pre>`, with a span for each run of cells with the same colors
+fn screen_html(snapshot: &Snapshot, html: &mut String) {
+    html.push_str("<pre>");
+    for y in 0..snapshot.size.1 {
+        let mut run = String::new();
+        let mut run_colors = None;
+        for x in 0..snapshot.size.0 {
+            let (ch, fg, bg) = cell(snapshot, x, y);
+            if run_colors != Some((fg, bg)) {
+                if let Some((fg, bg)) = run_colors {
+                    push_span(html, &run, fg, bg);
+                }
+                run.clear();
+                run_colors = Some((fg, bg));
+            }
+            run.push(ch);
+        }
+        if let Some((fg, bg)) = run_colors {
+            push_span(html, &run, fg, bg);
+        }
+        html.push('\n');
+    }
+    html.push_str("</pre>\n");
+}
+fn push_span(html: &mut String, text: &str, fg: Col, bg: Col) {
+    html.push_str("<span style=\"");
+    // a transparent fg hides the char, a transparent bg shows the page
+    if fg.3 == 0 {
+        html.push_str("color:transparent");
+    } else {
+        html.push_str("color:");
+        html.push_str(&hex(fg));
+    }
+    if bg.3 != 0 {
+        html.push_str(";background:");
+        html.push_str(&hex(bg));
+    }
+    html.push_str("\">");
+    html.push_str(&escape(text));
+    html.push_str("</span>");
+}
+// a snapshot as lines, without the spaces at their end
+fn screen_text(snapshot: &Snapshot) -> String {
+    let mut text = String::new();
+    for y in 0..snapshot.size.1 {
+        let line: String = (0..snapshot.size.0)
+            .map(|x| cell(snapshot, x, y).0)
+            .collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+// the part of a snapshot in a region, cut to the snapshot
+fn crop(snapshot: &Snapshot, region: Option<(u32, u32, u32, u32)>) -> Snapshot {
+    match region {
+        Some((x, y, width, height)) => snapshot.crop(x, y, width, height),
+        None => snapshot.clone(),
+    }
+}
+// the grid of a window (or a region of it, cut to the grid) as a snapshot
+fn grid_region(window: &Window, region: Option<(u32, u32, u32, u32)>) -> Snapshot {
+    let (grid_width, grid_height) = window.grid_size();
+    let (x, y, width, height) = region.unwrap_or((0, 0, grid_width, grid_height));
+    let (x, y) = (x.min(grid_width), y.min(grid_height));
+    let width = width.min(grid_width - x);
+    let height = height.min(grid_height - y);
+    window.take_snapshot(x, y, width, height)
+}
+// a color as #rrggbb
+fn hex(color: Col) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+// escapes what HTML would read as markup
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{colors::RED, Config};
+
+    #[test]
+    fn html_is_escaped() {
+        let mut window = Window::headless(Config::default());
+        window.print_at(0, 0, "<&\">", Some(RED), None);
+        let mut morgue = Morgue::new("<b>Rodney</b> & \"co\"");
+        morgue.add_window(&window, Some((0, 0, 4, 1)));
+        morgue.add_text("<h1>", ["a < b"]);
+        let html = morgue.to_html();
+        assert!(html.contains("<title>&lt;b&gt;Rodney&lt;/b&gt; &amp; &quot;co&quot;</title>"));
+        assert!(html.contains("<h2>&lt;h1&gt;</h2>"));
+        assert!(html.contains("<pre>a &lt; b\n</pre>"));
+        // the four cells have the same colors, so they are one span
+        assert!(html.contains("\">&lt;&amp;&quot;&gt;</span>"));
+        assert!(!html.contains("<b>"));
+    }
+    #[test]
+    fn text_lines_are_trimmed() {
+        let mut window = Window::headless(Config::default());
+        window.print_at(0, 0, "hi  ", None, None);
+        window.print_at(1, 1, "@", None, None);
+        assert_eq!(window_to_text(&window, Some((0, 0, 6, 3))), "hi\n @\n\n");
+        let mut morgue = Morgue::new("Rodney");
+        morgue.add_window(&window, Some((0, 0, 3, 2)));
+        morgue.add_text("Last messages", ["You die...  ", "Welcome!"]);
+        morgue.add_text("", ["no heading"]);
+        assert_eq!(
+            morgue.to_text(),
+            "Rodney\n\nhi\n @\n\nLast messages\nYou die...  \nWelcome!\n\nno heading\n\n"
+        );
+    }
+    #[test]
+    fn regions_are_cut_to_the_grid() {
+        let mut window = Window::headless(Config::default());
+        let (width, height) = window.grid_size();
+        window.print_at(width as i32 - 2, height as i32 - 1, "ab", None, None);
+        assert_eq!(
+            window_to_text(&window, Some((width - 2, height - 1, 10, 10))),
+            "ab\n"
+        );
+        assert_eq!(window_to_text(&window, Some((0, height + 5, 10, 10))), "");
+        let text = window_to_text(&window, None);
+        assert_eq!(text.lines().count(), height as usize);
+        assert!(text.ends_with("ab\n"));
+    }
+}
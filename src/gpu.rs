@@ -0,0 +1,1574 @@
+//! The wgpu renderer, behind the `gpu` feature (which is on by default)
+//! None of this is the source of truth: it can all be rebuilt from the CPU-side buffers of the `Window`
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use bytemuck::Zeroable;
+use image::{DynamicImage, ImageBuffer, Rgba};
+use wgpu::{util::DeviceExt, TextureUsages};
+use winit::{dpi::PhysicalSize, window::Window as WinitWindow};
+
+use crate::{block_on, image_layer::ImageLayer, now_ms, Config, InstanceData, RenderError, Window};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct Vertex {
+    position: [f32; 3],
+    uv: [f32; 2],
+}
+unsafe impl bytemuck::Pod for Vertex {}
+unsafe impl bytemuck::Zeroable for Vertex {}
+/// These are the vertices that occupy the whole screen
+const VERTICES: &[Vertex] = &[
+    // tri 1
+    Vertex {
+        position: [-1., -1., 0.],
+        uv: [0., 0. + 1.],
+    },
+    Vertex {
+        position: [-1., 1., 0.],
+        uv: [0., 1. - 1.],
+    },
+    Vertex {
+        position: [1., 1., 0.],
+        uv: [1., 1. - 1.],
+    },
+    // tri 2
+    Vertex {
+        position: [-1., -1., 0.],
+        uv: [0., 0. + 1.],
+    },
+    Vertex {
+        position: [1., -1., 0.],
+        uv: [1., 0. + 1.],
+    },
+    Vertex {
+        position: [1., 1., 0.],
+        uv: [1., 1. - 1.],
+    },
+];
+/// These are the vertices for individual floating letters
+const VERTICES_I: &[Vertex] = &[
+    // tri 1
+    Vertex {
+        position: [0., 0., 0.],
+        uv: [0., 0. + 1.],
+    },
+    Vertex {
+        position: [0., 1., 0.],
+        uv: [0., 1. - 1.],
+    },
+    Vertex {
+        position: [1., 1., 0.],
+        uv: [1., 1. - 1.],
+    },
+    // tri 2
+    Vertex {
+        position: [0., 0., 0.],
+        uv: [0., 0. + 1.],
+    },
+    Vertex {
+        position: [1., 0., 0.],
+        uv: [1., 0. + 1.],
+    },
+    Vertex {
+        position: [1., 1., 0.],
+        uv: [1., 1. - 1.],
+    },
+];
+// This is the layout of the screen vertices, I mostly copy-pasted it from the wgpu tutorial
+const VERTEX_LAYOUT: wgpu::VertexBufferLayout = /* more copy-pasting :3 */
+    wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress, // 1.
+        step_mode: wgpu::VertexStepMode::Vertex,                            // 2.
+        attributes: &[
+            // 3.
+            wgpu::VertexAttribute {
+                offset: 0,                             // 4.
+                shader_location: 0,                    // 5.
+                format: wgpu::VertexFormat::Float32x3, // 6.
+            },
+            wgpu::VertexAttribute {
+                offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress, //3//2
+                shader_location: 1,
+                format: wgpu::VertexFormat::Float32x2, //3
+            },
+        ],
+    };
+// the vertices of the image layers, which the window puts in clip space itself
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct ImageVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    // the alpha of the whole layer
+    alpha: f32,
+}
+unsafe impl bytemuck::Pod for ImageVertex {}
+unsafe impl bytemuck::Zeroable for ImageVertex {}
+const IMAGE_VERTEX_LAYOUT: wgpu::VertexBufferLayout = wgpu::VertexBufferLayout {
+    array_stride: std::mem::size_of::<ImageVertex>() as wgpu::BufferAddress,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &[
+        wgpu::VertexAttribute {
+            offset: 0,
+            shader_location: 0,
+            format: wgpu::VertexFormat::Float32x2,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+            shader_location: 1,
+            format: wgpu::VertexFormat::Float32x2,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            shader_location: 2,
+            format: wgpu::VertexFormat::Float32,
+        },
+    ],
+};
+// the uniforms of a post effect, laid out like `PostUniforms` in the shader (the params need 16 byte alignment)
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct PostUniforms {
+    resolution: [f32; 2],
+    time: f32,
+    _padding: f32,
+    params: [[f32; 4]; 2],
+}
+unsafe impl bytemuck::Pod for PostUniforms {}
+unsafe impl bytemuck::Zeroable for PostUniforms {}
+/// All the GPU-side ressources of a window
+/// None of this is the source of truth: it can all be rebuilt from the CPU-side buffers of the `Window`
+pub(crate) struct Gpu<'a> {
+    surface: wgpu::Surface<'a>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    set_texture: wgpu::Texture,
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    char_grid_texture: wgpu::Texture,
+    fg_texture: wgpu::Texture,
+    bg_texture: wgpu::Texture,
+    char_grid_size: wgpu::Extent3d,
+    instance_buffer: wgpu::Buffer,
+    instance_vertices: wgpu::Buffer,
+    instance_pipeline: wgpu::RenderPipeline,
+    surface_conf: wgpu::SurfaceConfiguration,
+    // draws the image layers (see the `image_layer` module)
+    image_pipeline: wgpu::RenderPipeline,
+    image_bind_group_layout: wgpu::BindGroupLayout,
+    image_sampler: wgpu::Sampler,
+    // the textures of the image layers, by the id of their image; they are uploaded the first time they are drawn
+    image_textures: HashMap<u64, (wgpu::Texture, wgpu::BindGroup)>,
+    // the post effects (see the `post_process` module)
+    post_bind_group_layout: wgpu::BindGroupLayout,
+    post_pipeline_layout: wgpu::PipelineLayout,
+    post_sampler: wgpu::Sampler,
+    // the pipeline of each effect's shader by its id, None if it didn't compile
+    post_pipelines: HashMap<u64, Option<wgpu::RenderPipeline>>,
+    // the uniforms of each effect of the chain, by its index
+    post_uniforms: Vec<wgpu::Buffer>,
+    // the frame is drawn into the first one, then the effects go back & forth between them; they are made when the first effect is added
+    post_targets: Option<[wgpu::TextureView; 2]>,
+    // why the last effect that didn't compile didn't compile, for `draw_gpu` to report
+    post_error: Option<String>,
+    // wgpu's device lost callback writes the reason in there, and we check it every frame
+    lost: Arc<Mutex<Option<String>>>,
+}
+impl<'a> Gpu<'a> {
+    // None if we couldn't get a GPU (no adapter, or the surface/device couldn't be made)
+    pub(crate) async fn new(
+        config_chargrid: &Config,
+        size: PhysicalSize<u32>,
+        window: &'static WinitWindow,
+        images: &[DynamicImage],
+    ) -> Option<Self> {
+        // save the padding here (define an alias)
+        let padding = config_chargrid.padding;
+        // Calculate the size of chars based on the assumption that the fonts are 256-character grids following cp437 encoding
+        let char_width = images[0].width() / 16;
+        let char_height = images[0].height() / 16;
+        // define more aliases
+        let cg_width = config_chargrid.size.0;
+        let cg_height = config_chargrid.size.1;
+        // calculate padding (this is magic math I could have had figured out properly but I instead fiddled with it till it worked).
+        let size_x =
+            (size.width - padding.0 * config_chargrid.scale.0) as f32 / (size.width) as f32;
+        let size_y =
+            (size.height - padding.1 * config_chargrid.scale.1) as f32 / (size.height) as f32;
+
+        // define more aliases
+        let max_instances = config_chargrid.max_instances;
+
+        // my instance configuration is mainly for wasm32 support
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            #[cfg(not(target_arch = "wasm32"))]
+            backends: wgpu::Backends::PRIMARY,
+            #[cfg(target_arch = "wasm32")]
+            backends: wgpu::Backends::GL,
+
+            ..Default::default()
+        });
+        // create the surface
+        let surface = instance.create_surface(window).ok()?;
+        // create the adapter
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::None,
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await?;
+        // create the device and the queue; we will use the device when creating ressources and the queue when issuing orders
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: wgpu::Features::empty(),
+                    // note more wasm32 things
+                    required_limits: if cfg!(target_arch = "wasm32") {
+                        wgpu::Limits::downlevel_webgl2_defaults()
+                    } else {
+                        wgpu::Limits::default()
+                    },
+                    // Mostly useful for debugging
+                    label: Some("device"),
+                    memory_hints: wgpu::MemoryHints::default(),
+                },
+                None,
+            )
+            .await
+            .ok()?;
+        // when the driver resets or the GPU goes away, wgpu tells us here; we can't do much from inside the callback, so we just write it down
+        let lost = Arc::new(Mutex::new(None));
+        {
+            let lost = lost.clone();
+            device.set_device_lost_callback(move |reason, message| {
+                if let Ok(mut lost) = lost.lock() {
+                    *lost = Some(format!("{:?}: {}", reason, message));
+                }
+            });
+        }
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .find(|f| {
+                // sRGB changes how colors are scaled, which makes low RGB values brighter
+                if config_chargrid.srgb {
+                    f.is_srgb()
+                } else {
+                    !f.is_srgb()
+                }
+            })
+            .copied()
+            // in case we didn't find anything that worked, we rely on the first element
+            .unwrap_or(surface_caps.formats[0]);
+        // render surface's config
+        let config = wgpu::SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode: surface_caps.present_modes[0],
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        // we convert all our font images to rgba8
+        let images_rgba8: Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> =
+            images.iter().map(|f| f.to_rgba8()).collect();
+        // we get the dimension of the first one (all font images must be the same size since I am using an array)
+        let dimensions = images_rgba8[0].dimensions();
+        // texture size of the font images on the GPU
+        let texture_size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            // note the array layers
+            depth_or_array_layers: images_rgba8.len() as u32,
+        };
+        // creates the GPU-side texture
+        let wgpu_side_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Format matches image format
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            // Copy DST is important since we dont actually create it with content
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("text texture"),
+            view_formats: &[],
+        });
+        // The size of the chargrid that covers the whole screen (except the padding)
+        let char_grid_size = wgpu::Extent3d {
+            width: cg_width,
+            height: cg_height,
+            depth_or_array_layers: 01,
+        };
+        // We create its texture
+        let char_grid_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: char_grid_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // It's a texture of single bytes, thus R8Unorm, which provides us with f32s scaled from 0. to 1. on the shader side
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("text grid texture"),
+            view_formats: &[],
+        });
+        // creates the texture which stores foreground colors for each grid character
+        let fg_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: char_grid_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // note the format
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("text fg texture"),
+            view_formats: &[],
+        });
+        // creates the texture which stores background colors for each grid character
+
+        let bg_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: char_grid_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("text bg texture"),
+            view_formats: &[],
+        });
+        // creates the texture which stores which font each character uses
+        // it is used in the shader to index into the font texture array
+        let set_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: char_grid_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // note that this gives us a limitation of 256 font files
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("text set texture"),
+            view_formats: &[],
+        });
+        // Generic pixel perfect sampler that clamps to the border
+        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        // view for the text texture, it is provided to the pipeline when it renders
+        let view = wgpu_side_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        // create other views
+        let view_char_grid = char_grid_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let fg_view = fg_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bg_view = bg_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let set_view = set_texture.create_view(&wgpu::TextureViewDescriptor::default()); //bg//bg
+        for i in images_rgba8.iter().enumerate() {
+            // ensure images are the same size
+            assert_eq!(
+                dimensions,
+                i.1.dimensions(),
+                "images must have the same size, sadly :("
+            );
+            // writes the font texture to the array
+            queue.write_texture(
+                wgpu::ImageCopyTextureBase {
+                    texture: &wgpu_side_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        // note the z-offset
+                        z: i.0 as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &i.1,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * dimensions.0),
+                    rows_per_image: Some(dimensions.1),
+                },
+                wgpu::Extent3d {
+                    width: texture_size.width,
+                    height: texture_size.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        // we don't write the grid textures here: wgpu zeroes them, and the window flags itself dirty after creating us
+        // so its CPU-side buffers get sent on the next draw (this is also how we recover from a device loss)
+        // includes the shaders, first the chargrid shader
+        let shader = include_str!("text_shader.wglsl");
+        // then the floating characters/instances shader
+        let mut shader_instance = include_str!("instance_shader.wglsl").to_owned();
+        // I could have went with constants but I instead just pre-filtered the shaders
+        // This is bad practice, but it Just Works
+        shader_instance = shader_instance.replace("$SC_WIDTH", format!("{}", size.width).as_str());
+        shader_instance =
+            shader_instance.replace("$SC_HEIGHT", format!("{}", size.height).as_str());
+        shader_instance = shader_instance.replace("$C_WIDTH", format!("{}", char_width).as_str());
+        shader_instance = shader_instance.replace("$C_HEIGHT", format!("{}", char_height).as_str());
+        shader_instance = shader_instance.replace("$PADDING_X", format!("{}", padding.0).as_str());
+        shader_instance = shader_instance.replace("$PADDING_Y", format!("{}", padding.1).as_str());
+        shader_instance = shader_instance.replace(
+            "$SCALE_FACTOR",
+            format!("{}", cg_width as f32 / (cg_height as f32)).as_str(),
+        );
+        shader_instance =
+            shader_instance.replace("$SCALE_X", format!("{}", config_chargrid.scale.0).as_str());
+        shader_instance =
+            shader_instance.replace("$SCALE_Y", format!("{}", config_chargrid.scale.1).as_str()); //X//0
+        let mut shader = shader.replace(
+            "$SCALE_FACTOR_X",
+            format!("f32({})", dimensions.0 as f32).as_str(),
+        );
+        shader = shader.replace(
+            "$SCALE_FACTOR_Y",
+            format!("f32({})", dimensions.1 as f32).as_str(),
+        );
+        shader = shader.replace(
+            "$CHARGRID",
+            format!("vec2<f32>(f32({}),f32({}))", cg_width, cg_height).as_str(),
+        );
+        // loads the shaders into WGPU
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("text shader"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let instance_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("instance shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_instance.into()),
+        });
+        // Here, I copied more things from the wgpu tutorial; each of these bindings echo textures & samplers from the shaders
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                // nooo i didnt copy paste anythiiing <- this is a lie
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        // This should match the filterable field of the
+                        // corresponding Texture entry above.
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5, //4
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("text rendering bind group layout"),
+            });
+        // creates the bind group with the layout we just provided
+        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    // here we actually point it to what we want it to be in the shaders
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&view_char_grid),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&fg_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&bg_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&set_view),
+                },
+            ],
+            label: Some("text rendering bind group"),
+        });
+        // we specify with the texture bind group layout our render pipeline layout
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("text render pipeline layout"),
+                bind_group_layouts: &[&texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        // the render pipeline is a vital part of WGPU rendering
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("text render pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                // specify the shader
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[VERTEX_LAYOUT],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                // also specify the shader
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    // blendstates are useful here to blend the config's background color with the color of the chargrid
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::OVER,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // no depth
+            depth_stencil: None,
+            multiview: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            cache: None,
+        });
+        // this is the render pipeline that renders floating characters / instances
+        let instance_render_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("text instances render pipeline"),
+                // it uses the same layout
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    // but not the same shader
+                    module: &instance_shader,
+                    entry_point: Some("vs_main"),
+                    // and it has an extra buffer
+                    buffers: &[VERTEX_LAYOUT, INSTANCE_LAYOUT],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    // apart from the shader, the fragment uses the same configuration
+                    module: &instance_shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::SrcAlpha,
+                                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent::OVER,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                // still no depth testing
+                depth_stencil: None,
+                multiview: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+            });
+        // the image layers have their own texture & sampler, and a pipeline that blends like the others
+        let image_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("image shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("image_shader.wglsl").into()),
+        });
+        let image_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("image bind group layout"),
+            });
+        let image_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("image render pipeline layout"),
+                bind_group_layouts: &[&image_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let image_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("image render pipeline"),
+            layout: Some(&image_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &image_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[IMAGE_VERTEX_LAYOUT],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &image_shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::OVER,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multiview: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            cache: None,
+        });
+        // the same kind of sampler as the fonts, so that pixel art stays sharp
+        let image_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        // the post effects read the previous result through a texture, a sampler & their uniforms
+        let post_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("post effect bind group layout"),
+            });
+        let post_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("post effect pipeline layout"),
+            bind_group_layouts: &[&post_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        // effects like curvature sample between pixels, so this one is smooth
+        let post_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // configure the surface
+        surface.configure(&device, &config);
+        // creates the vertex buffer for the triangles that cover the screen (note that we also do some math here to ensure padding works)
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("vertex buffer"),
+            contents: bytemuck::cast_slice(
+                &VERTICES
+                    .into_iter()
+                    .map(|f| {
+                        // apply padding
+                        Vertex {
+                            position: [
+                                f.position[0] * size_x,
+                                f.position[1] * size_y,
+                                // we don't care about z-position
+                                f.position[2],
+                            ],
+                            uv: [f.uv[0], f.uv[1]],
+                        }
+                    })
+                    .collect::<Vec<Vertex>>(),
+            ),
+            // we will not write to it, so it doesn't need `COPY_DST`
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        // we create the buffer of instances/floating characters; it starts out zeroed and the window fills it
+        let instances = vec![InstanceData::zeroed(); max_instances as usize];
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("instance buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            // note that in wgpu, instances use `BufferUsages::VERTEX`
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
+        });
+        // we create the buffer that contains the vertices
+        let instance_vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("instance vertice buffer"),
+            contents: bytemuck::cast_slice(&VERTICES_I),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        // we return the completed GPU state
+        Some(Self {
+            surface,
+            device,
+            queue,
+            instance_buffer,
+            render_pipeline,
+            bind_group: texture_bind_group,
+            vertex_buffer,
+            char_grid_texture,
+            fg_texture,
+            bg_texture,
+            char_grid_size,
+            set_texture,
+            instance_vertices,
+            instance_pipeline: instance_render_pipeline,
+            surface_conf: config,
+            image_pipeline,
+            image_bind_group_layout,
+            image_sampler,
+            // like the grid textures, the images are sent from the window's layers when they are drawn (again after a device loss)
+            image_textures: HashMap::new(),
+            // the effects are compiled when they are first drawn
+            post_bind_group_layout,
+            post_pipeline_layout,
+            post_sampler,
+            post_pipelines: HashMap::new(),
+            post_uniforms: Vec::new(),
+            post_targets: None,
+            post_error: None,
+            lost,
+        })
+    }
+}
+impl<'a> Window<'a> {
+    /// this function transmits all CPU-side buffers to the GPU
+    /// this is quite an heavy task; it would be a good idea to instead use memory-mapped regions instead of transfering everything
+    fn update(&mut self) {
+        let Some(gpu) = &self.gpu else {
+            return;
+        };
+        // redefine values for convenience
+        let cg_width = self.config_chargrid.size.0;
+        let cg_height = self.config_chargrid.size.1;
+        gpu.queue.write_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &gpu.char_grid_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.buffer_chars,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(cg_width),
+                rows_per_image: Some(cg_height),
+            },
+            gpu.char_grid_size,
+        );
+        gpu.queue.write_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &gpu.fg_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.buffer_colors_fg,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(cg_width * 4),
+                rows_per_image: Some(cg_height),
+            },
+            gpu.char_grid_size, //self.
+        );
+        gpu.queue.write_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &gpu.bg_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.buffer_colors_bg,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(cg_width * 4),
+                rows_per_image: Some(cg_height),
+            },
+            gpu.char_grid_size,
+        );
+        gpu.queue.write_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &gpu.set_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.set_buffer,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(cg_width),
+                rows_per_image: Some(cg_height),
+            },
+            gpu.char_grid_size,
+        );
+        gpu.queue.write_buffer(
+            &gpu.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+    }
+    // sends the images of the layers that the GPU doesn't have yet, and forgets the ones that aren't used anymore
+    fn upload_images(&mut self) {
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+        let max_size = gpu.device.limits().max_texture_dimension_2d;
+        let layers = self.background_image.iter().chain(&self.overlays);
+        for layer in layers.clone() {
+            let image = layer.image();
+            let (width, height) = image.dimensions();
+            // the images that can't be textures are skipped when drawing
+            if gpu.image_textures.contains_key(&layer.id())
+                || width == 0
+                || height == 0
+                || width > max_size
+                || height > max_size
+            {
+                continue;
+            }
+            let size = wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            };
+            let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // sRGB like the fonts
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                label: Some("image layer texture"),
+                view_formats: &[],
+            });
+            gpu.queue.write_texture(
+                wgpu::ImageCopyTextureBase {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                size,
+            );
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &gpu.image_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&gpu.image_sampler),
+                    },
+                ],
+                label: Some("image layer bind group"),
+            });
+            gpu.image_textures.insert(layer.id(), (texture, bind_group));
+        }
+        gpu.image_textures
+            .retain(|id, _| layers.clone().any(|layer| layer.id() == *id));
+    }
+    // records a pass that draws image layers over what's in the view
+    fn render_images(
+        &self,
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        layers: &[&ImageLayer],
+    ) {
+        // the layers we have a texture for, with their two triangles in clip space
+        let mut vertices = Vec::with_capacity(layers.len() * 6);
+        let mut drawn = Vec::with_capacity(layers.len());
+        for layer in layers {
+            let Some((_, bind_group)) = gpu.image_textures.get(&layer.id()) else {
+                continue;
+            };
+            let (left, top, right, bottom) = layer.pixel_rect(
+                (self.char_width, self.char_height),
+                self.config_chargrid.padding,
+                self.config_chargrid.scale,
+            );
+            let x = |x: f32| x / self.size.width as f32 * 2. - 1.;
+            let y = |y: f32| 1. - y / self.size.height as f32 * 2.;
+            let corner = |px: f32, py: f32, u: f32, v: f32| ImageVertex {
+                position: [x(px), y(py)],
+                uv: [u, v],
+                alpha: layer.alpha.clamp(0., 1.),
+            };
+            vertices.extend_from_slice(&[
+                corner(left, top, 0., 0.),
+                corner(left, bottom, 0., 1.),
+                corner(right, bottom, 1., 1.),
+                corner(left, top, 0., 0.),
+                corner(right, top, 1., 0.),
+                corner(right, bottom, 1., 1.),
+            ]);
+            drawn.push(bind_group);
+        }
+        if drawn.is_empty() {
+            return;
+        }
+        let vertex_buffer = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("image vertex buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("image render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&gpu.image_pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        for (i, bind_group) in drawn.into_iter().enumerate() {
+            let first = i as u32 * 6;
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(first..first + 6, 0..1);
+        }
+    }
+    // renders everything to the screen with the GPU, the surface problems we can fix by ourselves are fixed in here
+    pub(crate) fn draw_gpu(&mut self) -> Result<(), RenderError> {
+        // if the GPU went away, we rebuild everything from the CPU-side buffers, then tell the game
+        if let Some(reason) = self.take_device_lost() {
+            self.recreate_gpu();
+            return Err(RenderError::DeviceLost(reason));
+        }
+        // if *anything* is dirty, we update *everything*
+        // this is inefficient and should be improved
+        if self.dirty {
+            self.update();
+            self.dirty = false;
+        }
+        self.upload_images();
+        self.prepare_post_effects();
+        let Some(gpu) = &self.gpu else {
+            return Ok(());
+        };
+        let output = match gpu.surface.get_current_texture() {
+            Ok(output) => output,
+            // the surface doesn't match the window anymore (after a suspend or a monitor switch for instance), so we configure it again and the next frame should be fine
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.reconfigure();
+                return Ok(());
+            }
+            // the frame took too long to get, we just skip it
+            Err(wgpu::SurfaceError::Timeout) => return Ok(()),
+            // we can't fix the rest by ourselves, so the game gets to decide
+            Err(error) => return Err(RenderError::Surface(error)),
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        gpu.queue
+            .submit(std::iter::once(self.render_to(gpu, &view)));
+        output.present();
+        if let Some(error) = self.gpu.as_mut().and_then(|gpu| gpu.post_error.take()) {
+            return Err(RenderError::PostEffect(error));
+        }
+        Ok(())
+    }
+    // compiles the effects that weren't yet, makes the textures they need & sends their uniforms
+    fn prepare_post_effects(&mut self) {
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+        if self.post_effects.is_empty() {
+            return;
+        }
+        let format = gpu.surface_conf.format;
+        if gpu.post_targets.is_none() {
+            let target = |label| {
+                gpu.device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some(label),
+                        size: wgpu::Extent3d {
+                            width: self.size.width,
+                            height: self.size.height,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        // the same format as the surface, so that the pipelines work on both
+                        format,
+                        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            };
+            gpu.post_targets = Some([
+                target("post effect texture 1"),
+                target("post effect texture 2"),
+            ]);
+        }
+        for effect in &self.post_effects {
+            if gpu.post_pipelines.contains_key(&effect.id()) {
+                continue;
+            }
+            // wgpu panics on invalid shaders unless we catch the errors ourselves
+            gpu.device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let shader = gpu
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("post effect shader"),
+                    source: wgpu::ShaderSource::Wgsl(effect.shader().into()),
+                });
+            let pipeline = gpu
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("post effect pipeline"),
+                    layout: Some(&gpu.post_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        // the vertices come from their index
+                        buffers: &[],
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_main"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            // every pixel is replaced
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multiview: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    cache: None,
+                });
+            let pipeline = match block_on(gpu.device.pop_error_scope()) {
+                Some(error) => {
+                    gpu.post_error = Some(error.to_string());
+                    None
+                }
+                None => Some(pipeline),
+            };
+            gpu.post_pipelines.insert(effect.id(), pipeline);
+        }
+        while gpu.post_uniforms.len() < self.post_effects.len() {
+            gpu.post_uniforms
+                .push(gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("post effect uniforms"),
+                    size: std::mem::size_of::<PostUniforms>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }));
+        }
+        let time = ((now_ms() - self.start_time) / 1000.) as f32;
+        for (effect, buffer) in self.post_effects.iter().zip(&gpu.post_uniforms) {
+            let uniforms = PostUniforms {
+                resolution: [self.size.width as f32, self.size.height as f32],
+                time,
+                _padding: 0.,
+                params: effect.params,
+            };
+            gpu.queue
+                .write_buffer(buffer, 0, bytemuck::bytes_of(&uniforms));
+        }
+    }
+    // records the post effects that compiled, from the first target to `output`
+    fn render_post_effects(
+        &self,
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        effects: &[(usize, &wgpu::RenderPipeline)],
+        targets: &[wgpu::TextureView; 2],
+        output: &wgpu::TextureView,
+    ) {
+        for (pass, (index, pipeline)) in effects.iter().enumerate() {
+            let source = &targets[pass % 2];
+            let destination = if pass + 1 == effects.len() {
+                output
+            } else {
+                &targets[(pass + 1) % 2]
+            };
+            let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &gpu.post_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&gpu.post_sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: gpu.post_uniforms[*index].as_entire_binding(),
+                    },
+                ],
+                label: Some("post effect bind group"),
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("post effect render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: destination,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            // one triangle that covers everything
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+    // records everything we draw (background, images, grid, instances & post effects) into a view, which is the screen most of the time
+    fn render_to(&self, gpu: &Gpu, output: &wgpu::TextureView) -> wgpu::CommandBuffer {
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("text rendering command encoder"),
+            });
+        // with post effects, the frame goes into a texture for them first
+        let effects: Vec<(usize, &wgpu::RenderPipeline)> = self
+            .post_effects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, effect)| Some((i, gpu.post_pipelines.get(&effect.id())?.as_ref()?)))
+            .collect();
+        let targets = gpu.post_targets.as_ref().filter(|_| !effects.is_empty());
+        let view = targets.map_or(output, |targets| &targets[0]);
+        // clear render passs
+        // it fills the screen with config's background color
+        {
+            // setups the render pass; for a color clear pass, we dont need anything else
+            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("clear render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            // note how we turn it to floats (it was originally an u8 tuple)
+                            r: self.background_color.0 as f64 / 255.,
+                            g: self.background_color.1 as f64 / 255.,
+                            b: self.background_color.2 as f64 / 255.,
+                            a: self.background_color.3 as f64 / 255.,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+        }
+        // the background image goes under the grid, and shows through its transparent cells
+        if let Some(background) = &self.background_image {
+            self.render_images(gpu, &mut encoder, view, &[background]);
+        }
+        // render text pass
+        // this renders the  character grid
+        {
+            // setup the render pass
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("text render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            // since this render pass needs buffers+a pipeline, we provide them
+            render_pass.set_pipeline(&gpu.render_pipeline);
+            render_pass.set_bind_group(0, &gpu.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, gpu.vertex_buffer.slice(..));
+            // then we draw, 0..6 vertices since we have two 3-vertices triangles
+            // note the 0..1, since we don't use instances
+            render_pass.draw(0..6, 0..1);
+        }
+        // render instances pass
+        // this renders the instances / floating characters
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("instance render pass"),
+                // copy pasted
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            // sets the data
+            render_pass.set_pipeline(&gpu.instance_pipeline);
+            render_pass.set_bind_group(0, &gpu.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, gpu.instance_vertices.slice(..));
+            render_pass.set_vertex_buffer(1, gpu.instance_buffer.slice(..));
+            // draws the triangles
+            // note that we now use 0..self.instance_count instead of 0..1, since we now have an instance array
+            render_pass.draw(0..6, 0..self.instance_count);
+        }
+        // the overlays go on top of everything
+        let overlays: Vec<&ImageLayer> = self.overlays.iter().collect();
+        self.render_images(gpu, &mut encoder, view, &overlays);
+        if let Some(targets) = targets {
+            self.render_post_effects(gpu, &mut encoder, &effects, targets, output);
+        }
+        encoder.finish()
+    }
+    // renders the current frame again into an image, see `Window::screenshot`
+    // None on the web (we can't wait for the GPU there), or if the surface's format is an odd one
+    pub(crate) fn screenshot_gpu(&mut self) -> Option<image::RgbaImage> {
+        if cfg!(target_arch = "wasm32") {
+            return None;
+        }
+        if self.dirty {
+            self.update();
+            self.dirty = false;
+        }
+        self.upload_images();
+        self.prepare_post_effects();
+        let gpu = self.gpu.as_ref()?;
+        let format = gpu.surface_conf.format;
+        // the pipelines were made for the surface's format, so the copy must have the same (& it's one of these most of the time)
+        let bgra = match format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            _ => return None,
+        };
+        let (width, height) = (self.size.width, self.size.height);
+        let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("screenshot texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // rows of a texture copy must be aligned to 256 bytes, so there is some padding at the end of each row
+        let row = width * 4;
+        let padded_row =
+            row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("screenshot buffer"),
+            size: (padded_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("screenshot command encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        gpu.queue
+            .submit([self.render_to(gpu, &view), encoder.finish()]);
+        // we wait for the GPU to be done, then read the buffer back
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        gpu.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+        let mut pixels = Vec::with_capacity((row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for line in data.chunks(padded_row as usize) {
+                pixels.extend_from_slice(&line[..row as usize]);
+            }
+        }
+        buffer.unmap();
+        if bgra {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        image::RgbaImage::from_raw(width, height, pixels)
+    }
+    // the GPU part of `Window::resize`
+    pub(crate) fn resize_gpu(&mut self, new_size: PhysicalSize<u32>) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            // resizing on wasm32 crashes; I should investigate that
+            return;
+        }
+        // current resizing only stretches; it would be nice to keep the proportions constant
+        if new_size.width > 0 && new_size.height > 0 {
+            if let Some(gpu) = &mut self.gpu {
+                gpu.surface_conf.width = new_size.width;
+                gpu.surface_conf.height = new_size.height;
+                gpu.surface.configure(&gpu.device, &gpu.surface_conf);
+            }
+        }
+    }
+    /// re-applies the surface configuration, which is what wgpu wants us to do when the surface is lost or outdated
+    fn reconfigure(&mut self) {
+        if let Some(gpu) = &self.gpu {
+            gpu.surface.configure(&gpu.device, &gpu.surface_conf);
+        }
+    }
+    /// returns the reason of the device loss if the GPU device was lost since the last call
+    fn take_device_lost(&mut self) -> Option<String> {
+        self.gpu
+            .as_ref()
+            .and_then(|gpu| gpu.lost.lock().ok().and_then(|mut lost| lost.take()))
+    }
+    /// throws away every GPU ressource and creates them again, the CPU-side buffers are then re-uploaded on the next draw
+    /// if it fails, `draw` calls it again every second
+    pub(crate) fn recreate_gpu(&mut self) {
+        let Some(window) = self.window else {
+            return;
+        };
+        // the old surface must be gone before we create a new one for the same window
+        self.gpu = None;
+        self.gpu = block_on(Gpu::new(
+            &self.config_chargrid,
+            self.size,
+            window,
+            &self.images,
+        ));
+        // if the GPU is gone for good, we draw without it
+        #[cfg(feature = "software")]
+        if self.gpu.is_none() {
+            self.presenter = crate::raster::Presenter::new(window);
+        }
+        self.dirty = true;
+        // the new surface was configured for the original size, but the window may have changed since
+        self.resize(window.inner_size());
+    }
+}
+/// The layout of the instances, copy-pasted again from the wgpu tutorial
+const INSTANCE_LAYOUT: wgpu::VertexBufferLayout =
+    /* more copy-pasting :3 */ /* moare copy-pasting :> */
+    wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress, // 1.
+        step_mode: wgpu::VertexStepMode::Instance,                          // 2.
+        attributes: &[
+            // 3.//Vertex
+            wgpu::VertexAttribute {
+                offset: 0,                             // 4.
+                shader_location: 2,                    // 5.
+                format: wgpu::VertexFormat::Float32x2, // 6.
+            }, //0
+            wgpu::VertexAttribute {
+                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress, //3//2
+                shader_location: 3,
+                format: wgpu::VertexFormat::Uint8x2, //3
+            }, //1
+            wgpu::VertexAttribute {
+                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress
+                    + std::mem::size_of::<[u8; 2]>() as wgpu::BufferAddress, //3//2
+                shader_location: 4,
+                format: wgpu::VertexFormat::Unorm8x4, //3
+            }, //2
+            wgpu::VertexAttribute {
+                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress
+                    + std::mem::size_of::<[u8; 2 + 4]>() as wgpu::BufferAddress, //3//2
+                shader_location: 2 + 1 + 2,           // aka 5
+                format: wgpu::VertexFormat::Unorm8x4, //3
+            },
+        ],
+    };
@@ -0,0 +1,475 @@
+//! Recording what the grid shows over time, and playing it back (like ttyrec, but for our grid)
+//! Only the cells that changed are written down, with the time of their frame, so it's much lighter than a video
+//! and it's exact to the cell (see `Config::record_grid` or `Window::start_grid_recording`)
+//! A `GridPlayer` shows a recording in a window, with pause, seeking & speed controls; it's a `Yarl2Game` itself,
+//! so watching a recording is `run_game(GridPlayer::new(GridRecording::load("game.yrlg")?), config)`
+//! Notes:
+//! - only the grid is recorded (chars, sets & colors), not the instances
+//! - files with a grid of more than 1024×1024 cells are refused, since a bad file could ask for any amount of memory
+//!
+//! The file format is binary like the input recordings: a `YRLG` header, a version byte, the grid size (varints),
+//! then one record per frame: the time (a varint delta in ms from the previous frame), the amount of changed cells,
+//! and for each one its index (a varint delta from the previous change), a byte saying which of its parts changed & those parts
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use winit::keyboard::KeyCode;
+
+use crate::{
+    binary::{invalid, write_varint, Input},
+    now_ms, Col, NiceKeyboard, Window, WinitKey, Yarl2Game,
+};
+
+const MAGIC: &[u8; 4] = b"YRLG";
+const VERSION: u8 = 1;
+// the player keeps the whole grid every this many frames, so that seeking backwards doesn't replay everything
+const KEYFRAME_INTERVAL: usize = 1000;
+// what the player's keys seek by, in ms
+const SEEK_STEP: f64 = 5000.;
+// the most cells a recording read from a file can have (1024×1024), so that a bad size can't take all the memory
+const MAX_CELLS: usize = 1 << 20;
+
+/// A cell of the grid, as it's recorded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecordedCell {
+    /// the cp437 char
+    pub ch: u8,
+    pub set: u8,
+    pub fg: Col,
+    pub bg: Col,
+}
+/// The cells that changed in a frame
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GridFrame {
+    /// when the frame was drawn, in ms since the start of the recording
+    pub time: u64,
+    /// the cells that changed (their index is `x + y * width`), sorted by index
+    pub changes: Vec<(u32, RecordedCell)>,
+}
+/// A recording of the grid, the first frame has every cell that isn't empty
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GridRecording {
+    /// the size of the grid, in cells
+    pub size: (u32, u32),
+    /// the frames, sorted by time
+    pub frames: Vec<GridFrame>,
+}
+impl GridRecording {
+    /// creates an empty recording of a grid
+    pub fn new(size: (u32, u32)) -> Self {
+        Self {
+            size,
+            frames: Vec::new(),
+        }
+    }
+    /// the time of the last frame, in ms
+    pub fn duration(&self) -> u64 {
+        self.frames.last().map_or(0, |f| f.time)
+    }
+    /// writes the recording in the binary format
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        write_varint(&mut out, self.size.0 as u64);
+        write_varint(&mut out, self.size.1 as u64);
+        // what the cells are, so that we only write the parts that changed
+        let mut cells = vec![RecordedCell::default(); cell_count(self.size)];
+        let mut last_time = 0;
+        for frame in &self.frames {
+            write_varint(&mut out, frame.time.saturating_sub(last_time));
+            last_time = last_time.max(frame.time);
+            let mut changes = frame.changes.clone();
+            changes.sort_by_key(|f| f.0);
+            changes.dedup_by_key(|f| f.0);
+            write_varint(&mut out, changes.len() as u64);
+            let mut next_index = 0;
+            for (index, cell) in changes {
+                write_varint(&mut out, (index - next_index) as u64);
+                next_index = index + 1;
+                let old = cells
+                    .get_mut(index as usize)
+                    .ok_or_else(|| invalid("a change is outside of the grid"))?;
+                let flags = (old.ch != cell.ch) as u8
+                    | ((old.set != cell.set) as u8) << 1
+                    | ((old.fg != cell.fg) as u8) << 2
+                    | ((old.bg != cell.bg) as u8) << 3;
+                out.push(flags);
+                if flags & 1 != 0 {
+                    out.push(cell.ch);
+                }
+                if flags & 2 != 0 {
+                    out.push(cell.set);
+                }
+                if flags & 4 != 0 {
+                    out.extend_from_slice(&[cell.fg.0, cell.fg.1, cell.fg.2, cell.fg.3]);
+                }
+                if flags & 8 != 0 {
+                    out.extend_from_slice(&[cell.bg.0, cell.bg.1, cell.bg.2, cell.bg.3]);
+                }
+                *old = cell;
+            }
+        }
+        writer.write_all(&out)
+    }
+    /// reads a recording written by `write_to`
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut input = Input {
+            data: &data,
+            pos: 0,
+        };
+        if input.bytes(4)? != MAGIC {
+            return Err(invalid("not a grid recording"));
+        }
+        if input.u8()? != VERSION {
+            return Err(invalid("unsupported grid recording version"));
+        }
+        let mut dimension = || {
+            input
+                .varint()
+                .and_then(|f| u32::try_from(f).map_err(|_| invalid("the grid is too big")))
+        };
+        let size = (dimension()?, dimension()?);
+        if (size.0 as usize)
+            .checked_mul(size.1 as usize)
+            .is_none_or(|f| f > MAX_CELLS)
+        {
+            return Err(invalid("the grid is too big"));
+        }
+        let mut recording = Self::new(size);
+        let mut cells = vec![RecordedCell::default(); cell_count(size)];
+        let mut time: u64 = 0;
+        while !input.is_empty() {
+            time = input
+                .varint()?
+                .checked_add(time)
+                .ok_or_else(|| invalid("frame time too big"))?;
+            let count = input.varint()?;
+            let mut changes = Vec::new();
+            let mut next_index = 0;
+            for _ in 0..count {
+                let index = u32::try_from(input.varint()?)
+                    .ok()
+                    .and_then(|f| f.checked_add(next_index))
+                    .ok_or_else(|| invalid("a change is outside of the grid"))?;
+                next_index = index + 1;
+                let cell = cells
+                    .get_mut(index as usize)
+                    .ok_or_else(|| invalid("a change is outside of the grid"))?;
+                let flags = input.u8()?;
+                if flags & 1 != 0 {
+                    cell.ch = input.u8()?;
+                }
+                if flags & 2 != 0 {
+                    cell.set = input.u8()?;
+                }
+                if flags & 4 != 0 {
+                    let [r, g, b, a] = input.array()?;
+                    cell.fg = (r, g, b, a);
+                }
+                if flags & 8 != 0 {
+                    let [r, g, b, a] = input.array()?;
+                    cell.bg = (r, g, b, a);
+                }
+                changes.push((index, *cell));
+            }
+            recording.frames.push(GridFrame { time, changes });
+        }
+        Ok(recording)
+    }
+    /// saves the recording to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+    /// loads a recording from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::read_from(std::fs::File::open(path)?)
+    }
+}
+// the amount of cells of a grid, in usize so that big grids don't overflow
+fn cell_count(size: (u32, u32)) -> usize {
+    (size.0 as usize)
+        .checked_mul(size.1 as usize)
+        .expect("the grid is too big")
+}
+// every cell of the window's grid
+fn window_cells(window: &Window) -> Vec<RecordedCell> {
+    let color = |buffer: &[u8], index: usize| {
+        let n = &buffer[index * 4..index * 4 + 4];
+        (n[0], n[1], n[2], n[3])
+    };
+    (0..window.buffer_chars.len())
+        .map(|index| RecordedCell {
+            ch: window.buffer_chars[index],
+            set: window.set_buffer[index],
+            fg: color(&window.buffer_colors_fg, index),
+            bg: color(&window.buffer_colors_bg, index),
+        })
+        .collect()
+}
+/// Writes down what changed in the grid at each frame
+#[derive(Clone, Debug)]
+pub struct GridRecorder {
+    recording: GridRecording,
+    // the grid as of the last frame we kept
+    last: Vec<RecordedCell>,
+    // the time (in ms) of the first frame, the recording's times start from it
+    start: Option<f64>,
+}
+impl GridRecorder {
+    /// creates a recorder for a grid of that size (see `Window::grid_size`)
+    pub fn new(size: (u32, u32)) -> Self {
+        Self {
+            recording: GridRecording::new(size),
+            last: vec![RecordedCell::default(); cell_count(size)],
+            start: None,
+        }
+    }
+    /// records the window's grid, timed with the real clock
+    pub fn capture(&mut self, window: &Window) {
+        self.capture_at(window, now_ms());
+    }
+    /// records the window's grid as it was at this time (in ms, it can start at anything but must go up)
+    /// frames where nothing changed aren't kept
+    pub fn capture_at(&mut self, window: &Window, time: f64) {
+        let cells = window_cells(window);
+        if cells.len() != self.last.len() {
+            // not the grid we are recording
+            return;
+        }
+        let start = *self.start.get_or_insert(time);
+        let changes: Vec<(u32, RecordedCell)> = cells
+            .iter()
+            .zip(&self.last)
+            .enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(index, (new, _))| (index as u32, *new))
+            .collect();
+        // the first frame is always kept, so that the recording starts when the recorder does
+        if changes.is_empty() && !self.recording.frames.is_empty() {
+            return;
+        }
+        let time = ((time - start).max(0.).round() as u64).max(self.recording.duration());
+        self.recording.frames.push(GridFrame { time, changes });
+        self.last = cells;
+    }
+    /// the recording so far
+    pub fn recording(&self) -> &GridRecording {
+        &self.recording
+    }
+    /// stops recording & returns the recording
+    pub fn finish(self) -> GridRecording {
+        self.recording
+    }
+}
+// applies the changes of a frame to a grid
+fn apply(cells: &mut [RecordedCell], frame: &GridFrame) {
+    for (index, cell) in &frame.changes {
+        if let Some(old) = cells.get_mut(*index as usize) {
+            *old = *cell;
+        }
+    }
+}
+/// Plays a grid recording back in a window
+/// Keys (see `handle_keys`): space pauses, left & right seek by 5 seconds, up & down change the speed,
+/// home & end go to the start & the end, and the period shows the next frame (when paused)
+#[derive(Clone, Debug)]
+pub struct GridPlayer {
+    recording: GridRecording,
+    // the grid at `position`
+    cells: Vec<RecordedCell>,
+    // the index of the next frame to apply
+    next: usize,
+    // where we are in the recording, in ms
+    position: f64,
+    // the real time of the last update, to know how much time went by
+    last_update: Option<f64>,
+    // the grid before some frames (every KEYFRAME_INTERVAL frames), with the index of the frame
+    keyframes: Vec<(usize, Vec<RecordedCell>)>,
+    /// how fast the recording plays (2 is twice as fast)
+    /// Default: 1
+    pub speed: f64,
+    /// while true, the time doesn't move (seeking still works)
+    pub paused: bool,
+}
+impl GridPlayer {
+    /// starts playing the recording from its start
+    pub fn new(recording: GridRecording) -> Self {
+        let mut cells = vec![RecordedCell::default(); cell_count(recording.size)];
+        let mut keyframes = Vec::new();
+        for (index, frame) in recording.frames.iter().enumerate() {
+            if index % KEYFRAME_INTERVAL == 0 {
+                keyframes.push((index, cells.clone()));
+            }
+            apply(&mut cells, frame);
+        }
+        let mut player = Self {
+            cells: vec![RecordedCell::default(); cells.len()],
+            recording,
+            next: 0,
+            position: 0.,
+            last_update: None,
+            keyframes,
+            speed: 1.,
+            paused: false,
+        };
+        player.seek(0.);
+        player
+    }
+    /// where we are in the recording, in ms
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+    /// the length of the recording, in ms
+    pub fn duration(&self) -> f64 {
+        self.recording.duration() as f64
+    }
+    /// returns true once the last frame is shown
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+    /// the recording being played
+    pub fn recording(&self) -> &GridRecording {
+        &self.recording
+    }
+    /// the cell at a point of the recorded grid, as of `position`
+    pub fn cell_at(&self, x: u32, y: u32) -> Option<RecordedCell> {
+        if x >= self.recording.size.0 {
+            return None;
+        }
+        let index = (y as usize).checked_mul(self.recording.size.0 as usize)? + x as usize;
+        self.cells.get(index).copied()
+    }
+    /// pauses if playing, plays if paused
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+    /// moves the time forward with the real clock (times `speed`), unless paused
+    pub fn update(&mut self) {
+        let now = now_ms();
+        if let (Some(last), false) = (self.last_update, self.paused) {
+            self.seek(self.position + (now - last) * self.speed);
+        }
+        self.last_update = Some(now);
+    }
+    /// goes to a time (in ms) of the recording, it's clamped to the recording
+    pub fn seek(&mut self, time: f64) {
+        let time = time.clamp(0., self.duration());
+        let target = self
+            .recording
+            .frames
+            .partition_point(|f| f.time as f64 <= time);
+        if target < self.next {
+            // going backwards, so we start over from the closest keyframe
+            if let Some((index, cells)) = self.keyframes.iter().rev().find(|f| f.0 <= target) {
+                self.cells.clone_from(cells);
+                self.next = *index;
+            }
+        }
+        while self.next < target {
+            apply(&mut self.cells, &self.recording.frames[self.next]);
+            self.next += 1;
+        }
+        self.position = time;
+    }
+    /// pauses & shows the next frame
+    pub fn step_frame(&mut self) {
+        self.paused = true;
+        if let Some(frame) = self.recording.frames.get(self.next) {
+            self.seek(frame.time as f64);
+        }
+    }
+    /// the controls described on `GridPlayer`
+    pub fn handle_keys(&mut self, keyboard: &NiceKeyboard) {
+        let pressed = |key: KeyCode| keyboard.just_pressed(WinitKey::Code(key));
+        let repeated = |key: KeyCode| keyboard.is_repeated(WinitKey::Code(key));
+        if pressed(KeyCode::Space) {
+            self.toggle_pause();
+        }
+        if repeated(KeyCode::ArrowLeft) {
+            self.seek(self.position - SEEK_STEP);
+        }
+        if repeated(KeyCode::ArrowRight) {
+            self.seek(self.position + SEEK_STEP);
+        }
+        if pressed(KeyCode::ArrowUp) {
+            self.speed = (self.speed * 2.).min(64.);
+        }
+        if pressed(KeyCode::ArrowDown) {
+            self.speed = (self.speed / 2.).max(1. / 16.);
+        }
+        if pressed(KeyCode::Home) {
+            self.seek(0.);
+        }
+        if pressed(KeyCode::End) {
+            self.seek(self.duration());
+        }
+        if repeated(KeyCode::Period) {
+            self.step_frame();
+        }
+    }
+    /// shows the grid in the window, from its top left corner (what doesn't fit is cut)
+    pub fn draw(&self, window: &mut Window) {
+        let (width, height) = window.grid_size();
+        for y in 0..height.min(self.recording.size.1) {
+            for x in 0..width.min(self.recording.size.0) {
+                let cell = self.cells[x as usize + y as usize * self.recording.size.0 as usize];
+                let index = (x + y * width) as usize;
+                window.buffer_chars[index] = cell.ch;
+                window.set_buffer[index] = cell.set;
+                window.buffer_colors_fg[index * 4..index * 4 + 4]
+                    .copy_from_slice(&[cell.fg.0, cell.fg.1, cell.fg.2, cell.fg.3]);
+                window.buffer_colors_bg[index * 4..index * 4 + 4]
+                    .copy_from_slice(&[cell.bg.0, cell.bg.1, cell.bg.2, cell.bg.3]);
+            }
+        }
+        window.dirty = true;
+    }
+}
+// watching a recording is a game too
+impl Yarl2Game for GridPlayer {
+    fn pre_draw(&mut self, window: &mut Window<'static>, keyboard: &NiceKeyboard) {
+        self.handle_keys(keyboard);
+        self.update();
+        self.draw(window);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::tests::harness;
+
+    #[test]
+    fn grid_recordings_play_back_what_was_recorded() {
+        let mut recorded = harness();
+        let mut recorder = GridRecorder::new(recorded.window.grid_size());
+        let mut screens = Vec::new();
+        recorded.press_key(KeyCode::ArrowRight);
+        for frame in 0..12 {
+            if frame == 3 {
+                recorded.commit_text("recorded");
+            }
+            recorded.step();
+            recorder.capture_at(&recorded.window, frame as f64 * 50.);
+            screens.push(recorded.screen_text());
+        }
+        let mut bytes = Vec::new();
+        recorder.finish().write_to(&mut bytes).unwrap();
+        let mut player = GridPlayer::new(GridRecording::read_from(bytes.as_slice()).unwrap());
+        assert_eq!(player.duration(), 550.);
+        // a second harness, only for its window
+        let mut playback = harness();
+        // forward, then backwards to go through the keyframes
+        for frame in (0..12).chain((0..12).rev()) {
+            player.seek(frame as f64 * 50.);
+            player.draw(&mut playback.window);
+            assert_eq!(playback.screen_text(), screens[frame], "frame {}", frame);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Runs a `Yarl2Game` without a window or a GPU, for tests
+//! The harness owns a headless `Window` (see `Window::headless`) and a `NiceKeyboard` that only gets the inputs you give it,
+//! and frames only happen when you call `step`
+//!
+//! ```ignore
+//! let mut harness = TestHarness::new(MyGame::default(), Config::default());
+//! harness.tap_key(KeyCode::KeyI);
+//! harness.step();
+//! harness.assert_text_at(0, 0, "Inventory");
+//! ```
+use winit::keyboard::{Key, KeyCode, NativeKey, NativeKeyCode, PhysicalKey, SmolStr};
+
+use crate::{
+    feed_input, keys::NAMED_KEYS, Config, InputEvent, Modifiers, NiceKeyboard, Window,
+    WinitLogicalKey, WinitMouseButton, Yarl2Game,
+};
+
+/// Drives a game frame by frame, with made up inputs
+pub struct TestHarness<T: Yarl2Game> {
+    pub game: T,
+    pub window: Window<'static>,
+    pub keyboard: NiceKeyboard,
+    /// how long a frame lasts (in ms), key repeats & double clicks are timed with it instead of the real clock
+    /// Default: 1000 / 60
+    pub frame_duration: f64,
+    // the amount of frames that were stepped
+    frame: u64,
+}
+impl<T: Yarl2Game> TestHarness<T> {
+    /// creates the harness; the fonts of the config are still decoded, since the cell size depends on them
+    pub fn new(game: T, config: Config) -> Self {
+        Self {
+            game,
+            keyboard: NiceKeyboard::for_config(&config),
+            window: Window::headless(config),
+            frame_duration: 1000. / 60.,
+            frame: 0,
+        }
+    }
+    /// runs one frame, the same way the event loop does (pre_draw, draw, post_draw)
+    /// returns true if the game asked to exit (`close` has been called then)
+    /// frame n happens at n * `frame_duration` ms, so held keys repeat the same way on every run
+    pub fn step(&mut self) -> bool {
+        self.keyboard
+            .update_repeats(self.frame as f64 * self.frame_duration);
+        self.game.pre_draw(&mut self.window, &self.keyboard);
+        self.frame += 1;
+        // there is no GPU, so this only resets the dirty flag (and records the grid, see `Config::record_grid`)
+        let _ = self.window.draw();
+        self.keyboard.end_frame();
+        self.game.post_draw();
+        if self.game.should_exit() {
+            self.game.close();
+            return true;
+        }
+        false
+    }
+    /// runs `count` frames, stops early if the game asked to exit (and returns true then)
+    pub fn step_frames(&mut self, count: u64) -> bool {
+        (0..count).any(|_| self.step())
+    }
+    /// the amount of frames that were stepped
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+    /// sends an input, it will be seen by the next `step`
+    pub fn input(&mut self, event: InputEvent) {
+        feed_input(&mut self.game, &mut self.window, &mut self.keyboard, event);
+    }
+    /// gives or takes the focus, like the window would (losing it releases everything that is held)
+    pub fn set_focused(&mut self, focused: bool) {
+        if !focused {
+            for input in self.keyboard.releases() {
+                self.input(input);
+            }
+        }
+        self.game.focus_changed(focused, &mut self.window);
+    }
+    /// sets the held modifiers
+    pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.input(InputEvent::ModifiersChanged(modifiers));
+    }
+    /// presses a key; letters, digits & a few others also type their text (letters are uppercase if shift is held)
+    pub fn press_key(&mut self, key: KeyCode) {
+        let (logical, text) = logical_key(key, self.keyboard.modifiers.shift);
+        self.input(InputEvent::KeyPressed {
+            key: PhysicalKey::Code(key),
+            logical,
+            text,
+            repeat: false,
+        });
+    }
+    /// releases a key
+    pub fn release_key(&mut self, key: KeyCode) {
+        let (logical, _) = logical_key(key, self.keyboard.modifiers.shift);
+        self.input(InputEvent::KeyReleased {
+            key: PhysicalKey::Code(key),
+            logical,
+        });
+    }
+    /// presses then releases a key; both happen before the next frame, so only the just_pressed/just_released sets will see it
+    pub fn tap_key(&mut self, key: KeyCode) {
+        self.press_key(key);
+        self.release_key(key);
+    }
+    /// types text, one key press (& release) per char
+    /// chars that aren't on a key we know of use an unidentified physical key
+    pub fn type_text(&mut self, text: &str) {
+        for ch in text.chars() {
+            let key = physical_key(ch);
+            let logical = Key::Character(SmolStr::new(ch.encode_utf8(&mut [0; 4])));
+            self.input(InputEvent::KeyPressed {
+                key,
+                logical: logical.clone(),
+                text: Some(ch.to_string()),
+                repeat: false,
+            });
+            self.input(InputEvent::KeyReleased { key, logical });
+        }
+    }
+    /// sends text as if the IME committed it
+    pub fn commit_text(&mut self, text: &str) {
+        self.input(InputEvent::ImeCommit(text.to_owned()));
+    }
+    /// moves the mouse, in cells (fractional parts are where the cursor is inside the cell)
+    pub fn mouse_move(&mut self, x: f32, y: f32) {
+        self.input(InputEvent::MouseMoved(x, y));
+    }
+    /// presses a mouse button where the mouse currently is
+    pub fn mouse_press(&mut self, button: WinitMouseButton) {
+        self.input(InputEvent::MousePressed(button));
+    }
+    /// releases a mouse button
+    pub fn mouse_release(&mut self, button: WinitMouseButton) {
+        self.input(InputEvent::MouseReleased(button));
+    }
+    /// moves the mouse to the middle of a cell, then presses & releases a button
+    pub fn click(&mut self, x: i32, y: i32, button: WinitMouseButton) {
+        self.mouse_move(x as f32 + 0.5, y as f32 + 0.5);
+        self.mouse_press(button);
+        self.mouse_release(button);
+    }
+    /// reads `len` chars on a line of the grid
+    pub fn text_at(&self, x: u32, y: u32, len: usize) -> String {
+        self.window.text_at(x, y, len)
+    }
+    /// the whole grid as text, one line per row
+    pub fn screen_text(&self) -> String {
+        let (width, height) = self.window.grid_size();
+        (0..height)
+            .map(|y| self.window.text_at(0, y, width as usize))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+    /// panics (with the whole grid) if the text at that point isn't `expected`
+    #[track_caller]
+    pub fn assert_text_at(&self, x: u32, y: u32, expected: &str) {
+        let found = self.text_at(x, y, expected.chars().count());
+        if found != expected {
+            let dump: Vec<String> = self
+                .screen_text()
+                .lines()
+                .enumerate()
+                .map(|(i, line)| format!("{:>3}|{}", i, line))
+                .collect();
+            panic!(
+                "expected {:?} at ({}, {}) but found {:?} (frame {})\n{}",
+                expected,
+                x,
+                y,
+                found,
+                self.frame,
+                dump.join("\n")
+            );
+        }
+    }
+}
+// what a key would give on a qwerty layout, only used to make up inputs (here & for the terminal, which only gives us text)
+pub(crate) fn logical_key(key: KeyCode, shift: bool) -> (WinitLogicalKey, Option<String>) {
+    let name = format!("{:?}", key);
+    let ch = match (name.strip_prefix("Key"), name.strip_prefix("Digit")) {
+        (Some(letter), _) if letter.len() == 1 => {
+            letter
+                .chars()
+                .next()
+                .map(|f| if shift { f } else { f.to_ascii_lowercase() })
+        }
+        (_, Some(digit)) if digit.len() == 1 => digit.chars().next(),
+        _ => None,
+    };
+    if let Some(ch) = ch {
+        return (
+            Key::Character(SmolStr::new(ch.encode_utf8(&mut [0; 4]))),
+            Some(ch.to_string()),
+        );
+    }
+    let text = match key {
+        KeyCode::Space => Some(" "),
+        KeyCode::Enter | KeyCode::NumpadEnter => Some("\r"),
+        KeyCode::Tab => Some("\t"),
+        _ => None,
+    };
+    // named keys have the same names as the key codes
+    let logical = NAMED_KEYS
+        .iter()
+        .find(|f| format!("{:?}", f) == name)
+        .map(|f| Key::Named(*f))
+        .unwrap_or(Key::Unidentified(NativeKey::Unidentified));
+    (logical, text.map(|f| f.to_owned()))
+}
+// the key that types a char, on a qwerty layout
+pub(crate) fn physical_key(ch: char) -> PhysicalKey {
+    let name = match ch {
+        'a'..='z' | 'A'..='Z' => format!("Key{}", ch.to_ascii_uppercase()),
+        '0'..='9' => format!("Digit{}", ch),
+        ' ' => "Space".to_owned(),
+        _ => return PhysicalKey::Unidentified(NativeKeyCode::Unidentified),
+    };
+    crate::keys::key_code_from_name(&name)
+        .map(PhysicalKey::Code)
+        .unwrap_or(PhysicalKey::Unidentified(NativeKeyCode::Unidentified))
+}
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::WinitKey;
+
+    // a tiny game: the typed text on the first row, and an `@` on the second one that the arrows move
+    // the tests of the recordings play it too
+    #[derive(Default)]
+    pub(crate) struct Game {
+        pub(crate) typed: String,
+        pub(crate) x: i32,
+        pub(crate) double_clicks: u32,
+        pub(crate) exit: bool,
+    }
+    impl Yarl2Game for Game {
+        fn pre_draw(&mut self, window: &mut Window<'static>, keyboard: &NiceKeyboard) {
+            if keyboard.is_repeated(WinitKey::Code(KeyCode::ArrowRight)) {
+                self.x += 1;
+            }
+            if keyboard.double_clicked {
+                self.double_clicks += 1;
+            }
+            self.exit |= keyboard.just_pressed(WinitKey::Code(KeyCode::Escape));
+            window.clear();
+            window.print_at(0, 0, &self.typed, None, None);
+            window.set_char_at(self.x, 1, '@');
+        }
+        fn text_input(&mut self, character: char, _window: &mut Window) {
+            self.typed.push(character);
+        }
+        fn should_exit(&mut self) -> bool {
+            self.exit
+        }
+    }
+    // a harness whose frames last 50ms, with keys that repeat after 300ms & then every 50ms
+    pub(crate) fn harness() -> TestHarness<Game> {
+        let mut harness = TestHarness::new(Game::default(), Config::default());
+        harness.frame_duration = 50.;
+        harness.keyboard.repeat_delay = 300.;
+        harness.keyboard.repeat_interval = 50.;
+        harness
+    }
+
+    #[test]
+    fn inputs_reach_the_grid() {
+        let mut harness = harness();
+        harness.type_text("hi");
+        harness.tap_key(KeyCode::ArrowRight);
+        assert!(!harness.step());
+        harness.assert_text_at(0, 0, "hi");
+        harness.assert_text_at(1, 1, "@");
+        assert_eq!(harness.frame(), 1);
+    }
+    #[test]
+    fn held_keys_repeat_with_the_frames() {
+        let run = || {
+            let mut harness = harness();
+            harness.press_key(KeyCode::ArrowRight);
+            harness.step_frames(10);
+            harness.game.x
+        };
+        // pressed on frame 0, then repeated on frames 6 to 9
+        assert_eq!(run(), 5);
+        assert_eq!(run(), 5);
+    }
+    #[test]
+    fn double_clicks_are_timed_with_the_frames() {
+        let mut harness = harness();
+        harness.click(3, 3, WinitMouseButton::Left);
+        harness.step();
+        harness.click(3, 3, WinitMouseButton::Left);
+        harness.step();
+        assert_eq!(harness.game.double_clicks, 1);
+        // 20 frames of 50ms are too long for a double click
+        harness.click(3, 3, WinitMouseButton::Left);
+        harness.step_frames(20);
+        harness.click(3, 3, WinitMouseButton::Left);
+        harness.step();
+        assert_eq!(harness.game.double_clicks, 1);
+    }
+    #[test]
+    fn escape_exits() {
+        let mut harness = harness();
+        harness.tap_key(KeyCode::Escape);
+        assert!(harness.step());
+    }
+}
@@ -0,0 +1,102 @@
+//! Real images drawn with the grid: a background behind it, which shows through its transparent cells (see `Window::set_background_image`),
+//! and overlays on top of everything (see `Window::add_overlay`), for splash screens, parallax backdrops or map overviews
+//!
+//! ```ignore
+//! let backdrop = ImageLayer::load(&Font::Path("backdrop.png".into()))?.covering(window);
+//! window.set_background_image(Some(backdrop));
+//! let mut map = ImageLayer::new(map_image);
+//! map.position = (40., 2.);
+//! map.alpha = 0.8;
+//! window.add_overlay(map);
+//! ```
+//! Notes:
+//! - the images are drawn with the GPU (they get uploaded once, then stay there until they aren't used anymore) and by the software renderer
+//! - they are sampled like the fonts (nearest pixel, sRGB), so pixel art stays sharp
+//! - the terminal backend can't show them
+#[cfg(feature = "gpu")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use image::{DynamicImage, RgbaImage};
+
+use crate::{Font, Window};
+
+// every image gets its own id, so that the GPU knows which ones it already has
+#[cfg(feature = "gpu")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// An image, with where & how it's drawn
+/// cloning it is cheap, and the clones share the image (& its texture on the GPU)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageLayer {
+    image: Arc<RgbaImage>,
+    #[cfg(feature = "gpu")]
+    id: u64,
+    /// where its top left corner is, in cells (like `InstanceData::position`), it can be fractional or negative
+    /// Default: (0, 0)
+    pub position: (f32, f32),
+    /// how big a pixel of the image is, in pixels of the font (so 1 draws it at the font's resolution)
+    /// Default: (1, 1)
+    pub scale: (f32, f32),
+    /// how opaque the whole image is, from 0 (invisible) to 1
+    /// Default: 1
+    pub alpha: f32,
+}
+impl ImageLayer {
+    /// creates a layer out of an image
+    pub fn new(image: DynamicImage) -> Self {
+        Self {
+            image: Arc::new(image.to_rgba8()),
+            #[cfg(feature = "gpu")]
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            position: (0., 0.),
+            scale: (1., 1.),
+            alpha: 1.,
+        }
+    }
+    /// loads the image of a layer from anything a font can be loaded from (a path, bytes or an image)
+    pub fn load(source: &Font) -> image::ImageResult<Self> {
+        Ok(Self::new(source.decode()?))
+    }
+    /// the image
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+    /// stretches the layer over the whole window, padding included
+    pub fn covering(mut self, window: &Window) -> Self {
+        let config = &window.config_chargrid;
+        // the grid starts after half of the padding
+        self.position = (
+            -(config.padding.0 as f32 / 2.) / window.char_width as f32,
+            -(config.padding.1 as f32 / 2.) / window.char_height as f32,
+        );
+        self.scale = (
+            window.size.width as f32 / (config.scale.0 * self.image.width().max(1)) as f32,
+            window.size.height as f32 / (config.scale.1 * self.image.height().max(1)) as f32,
+        );
+        self
+    }
+    // identifies the image for the GPU's texture cache
+    #[cfg(feature = "gpu")]
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+    // the rectangle it covers in the pixels we render at (the window's size before it got stretched): left, top, right, bottom
+    // it depends on the size of a char, the padding & the scale of the window
+    pub(crate) fn pixel_rect(
+        &self,
+        char_size: (u32, u32),
+        padding: (u32, u32),
+        scale: (u32, u32),
+    ) -> (f32, f32, f32, f32) {
+        let (scale_x, scale_y) = (scale.0 as f32, scale.1 as f32);
+        let left = (self.position.0 * char_size.0 as f32 + padding.0 as f32 / 2.) * scale_x;
+        let top = (self.position.1 * char_size.1 as f32 + padding.1 as f32 / 2.) * scale_y;
+        (
+            left,
+            top,
+            left + self.image.width() as f32 * self.scale.0 * scale_x,
+            top + self.image.height() as f32 * self.scale.1 * scale_y,
+        )
+    }
+}
@@ -0,0 +1,591 @@
+//! Maps named actions ("move_n", "pickup", "ui_select"...) to keys & mouse buttons, so that games don't hardcode keys
+//! Bindings can be changed at runtime and saved to a text file that looks like this:
+//! ```text
+//! # comments start with a #
+//! move_n = K, ArrowUp, Numpad8
+//! save = Ctrl+S
+//! ui_select = Enter, MouseLeft
+//! ```
+//! Actions belong to a context (see `InputMap::context`): the ones starting with `ui_` are in `"ui"`, the others in `"game"`,
+//! and a binding is only a conflict if it triggers more than one action of the same context
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use winit::keyboard::KeyCode;
+
+use crate::{
+    keys::{key_code_from_name, split_modifiers, Chord, ChordParseError},
+    InputEvent, Modifiers, NiceKeyboard, WinitKey, WinitMouseButton,
+};
+
+/// Something that triggers an action
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    /// a key with modifiers
+    Key(Chord),
+    /// a mouse button with modifiers
+    Mouse {
+        button: WinitMouseButton,
+        modifiers: Modifiers,
+    },
+}
+impl Binding {
+    /// returns true if the binding is held
+    pub fn is_pressed(&self, keyboard: &NiceKeyboard) -> bool {
+        match self {
+            Binding::Key(chord) => chord.is_pressed(keyboard),
+            Binding::Mouse { button, modifiers } => {
+                keyboard.modifiers == *modifiers && keyboard.mouse_buttons.contains(button)
+            }
+        }
+    }
+    /// returns true if the binding was pressed since the last frame
+    pub fn is_just_pressed(&self, keyboard: &NiceKeyboard) -> bool {
+        match self {
+            Binding::Key(chord) => chord.is_just_pressed(keyboard),
+            Binding::Mouse { button, modifiers } => {
+                keyboard.modifiers == *modifiers && keyboard.mouse_just_pressed.contains(button)
+            }
+        }
+    }
+    /// returns true if the binding was pressed since the last frame, or is a held key that repeated this frame
+    /// mouse buttons don't repeat
+    pub fn is_repeated(&self, keyboard: &NiceKeyboard) -> bool {
+        match self {
+            Binding::Key(chord) => chord.is_repeated(keyboard),
+            Binding::Mouse { .. } => self.is_just_pressed(keyboard),
+        }
+    }
+}
+// the names of mouse buttons in files, `Other` buttons are written `Mouse<number>`
+const MOUSE_NAMES: &[(&str, WinitMouseButton)] = &[
+    ("MouseLeft", WinitMouseButton::Left),
+    ("MouseRight", WinitMouseButton::Right),
+    ("MouseMiddle", WinitMouseButton::Middle),
+    ("MouseBack", WinitMouseButton::Back),
+    ("MouseForward", WinitMouseButton::Forward),
+];
+impl FromStr for Binding {
+    type Err = ChordParseError;
+    /// parses `Ctrl+S` or `Shift+MouseRight`-like strings
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = split_modifiers(s)?;
+        let mouse = MOUSE_NAMES
+            .iter()
+            .find(|f| f.0.eq_ignore_ascii_case(key))
+            .map(|f| f.1)
+            .or_else(|| {
+                key.get(..5)
+                    .filter(|f| f.eq_ignore_ascii_case("mouse"))
+                    .and_then(|_| key[5..].parse().ok())
+                    .map(WinitMouseButton::Other)
+            });
+        if let Some(button) = mouse {
+            return Ok(Binding::Mouse { button, modifiers });
+        }
+        let key =
+            key_code_from_name(key).ok_or_else(|| ChordParseError::UnknownKey(key.to_owned()))?;
+        Ok(Binding::Key(Chord { key, modifiers }))
+    }
+}
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(chord) => write!(f, "{}", chord),
+            Binding::Mouse { button, modifiers } => {
+                match MOUSE_NAMES.iter().find(|f| f.1 == *button) {
+                    Some((name, _)) => write!(f, "{}{}", modifiers, name),
+                    None => match button {
+                        WinitMouseButton::Other(n) => write!(f, "{}Mouse{}", modifiers, n),
+                        _ => unreachable!("every other button has a name"),
+                    },
+                }
+            }
+        }
+    }
+}
+/// An error in an input map file, with the line (starting at 1) it happened on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMapParseError {
+    pub line: usize,
+    pub kind: InputMapParseErrorKind,
+}
+/// What went wrong in an input map file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputMapParseErrorKind {
+    /// the line had no `=`
+    MissingEquals,
+    /// the line had no action name before the `=`
+    MissingAction,
+    /// one of the bindings couldn't be read
+    Binding(ChordParseError),
+}
+impl Display for InputMapParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            InputMapParseErrorKind::MissingEquals => write!(f, "expected `action = bindings`"),
+            InputMapParseErrorKind::MissingAction => write!(f, "missing action name"),
+            InputMapParseErrorKind::Binding(error) => write!(f, "{}", error),
+        }
+    }
+}
+impl std::error::Error for InputMapParseError {}
+/// Maps actions to the bindings that trigger them
+/// An action can have many bindings, and a binding can trigger many actions (see `conflicts`)
+#[derive(Clone, Debug, Default)]
+pub struct InputMap {
+    // we keep the actions in order so that saved files don't get shuffled around
+    actions: Vec<(String, Vec<Binding>)>,
+    // the action whose next binding we are capturing
+    rebinding: Option<String>,
+    // the contexts given with `set_context`, the other actions get theirs from their name
+    contexts: Vec<(String, String)>,
+}
+impl InputMap {
+    /// creates an empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// creates a map from a preset table (see `VI_KEYS`, `NUMPAD` & `WASD`)
+    /// panics if the table has a binding we can't read, which shouldn't happen with the provided tables
+    pub fn from_preset(preset: &[(&str, &[&str])]) -> Self {
+        let mut map = Self::new();
+        map.add_preset(preset);
+        map
+    }
+    /// adds the bindings of a preset table to this map, which lets you combine presets
+    pub fn add_preset(&mut self, preset: &[(&str, &[&str])]) {
+        for (action, bindings) in preset {
+            for binding in bindings.iter() {
+                let binding = binding
+                    .parse()
+                    .unwrap_or_else(|e| panic!("bad binding `{}` in preset: {}", binding, e));
+                self.bind(action, binding);
+            }
+        }
+    }
+    /// the vi-keys scheme (hjkl + yubn)
+    pub fn vi_keys() -> Self {
+        Self::from_preset(VI_KEYS)
+    }
+    /// the numpad scheme
+    pub fn numpad() -> Self {
+        Self::from_preset(NUMPAD)
+    }
+    /// the WASD scheme (with QEZC for diagonals)
+    pub fn wasd() -> Self {
+        Self::from_preset(WASD)
+    }
+    // returns the index of the action, if it exists
+    fn find(&self, action: &str) -> Option<usize> {
+        self.actions.iter().position(|f| f.0 == action)
+    }
+    /// adds a binding to an action (creating the action if needed), does nothing if the action already has it
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        match self.find(action) {
+            Some(i) => {
+                if !self.actions[i].1.contains(&binding) {
+                    self.actions[i].1.push(binding);
+                }
+            }
+            None => self.actions.push((action.to_owned(), vec![binding])),
+        }
+    }
+    /// same as `bind`, but reads the binding from a string like `"Ctrl+S"`
+    pub fn bind_str(&mut self, action: &str, binding: &str) -> Result<(), ChordParseError> {
+        self.bind(action, binding.parse()?);
+        Ok(())
+    }
+    /// removes a binding from an action
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(i) = self.find(action) {
+            self.actions[i].1.retain(|f| *f != binding);
+        }
+    }
+    /// removes every binding of an action (the action itself stays, so that it's still saved)
+    pub fn clear_action(&mut self, action: &str) {
+        if let Some(i) = self.find(action) {
+            self.actions[i].1.clear();
+        }
+    }
+    /// returns the bindings of an action (empty if the action doesn't exist)
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.find(action)
+            .map_or(&[][..], |i| self.actions[i].1.as_slice())
+    }
+    /// returns the names of all the actions, in order
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|f| f.0.as_str())
+    }
+    /// returns true if any binding of the action is held
+    pub fn is_action_pressed(&self, action: &str, keyboard: &NiceKeyboard) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_pressed(keyboard))
+    }
+    /// returns true if any binding of the action was pressed since the last frame
+    pub fn just_triggered(&self, action: &str, keyboard: &NiceKeyboard) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_pressed(keyboard))
+    }
+    /// like `just_triggered`, but also true while a key of the action is held, at the repeat rate of the keyboard
+    /// this is what you want for moving around
+    pub fn triggered_or_repeated(&self, action: &str, keyboard: &NiceKeyboard) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_repeated(keyboard))
+    }
+    /// returns every action that was triggered since the last frame
+    pub fn triggered_actions<'a>(
+        &'a self,
+        keyboard: &'a NiceKeyboard,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.actions
+            .iter()
+            .filter(|f| f.1.iter().any(|binding| binding.is_just_pressed(keyboard)))
+            .map(|f| f.0.as_str())
+    }
+    /// starts capturing the next key or mouse button, which will replace the bindings of the action
+    /// call `poll_rebind` every frame until it returns something
+    pub fn start_rebind(&mut self, action: &str) {
+        self.rebinding = Some(action.to_owned());
+    }
+    /// stops capturing without changing anything
+    pub fn cancel_rebind(&mut self) {
+        self.rebinding = None;
+    }
+    /// returns the action we are capturing a binding for, if any
+    pub fn rebinding(&self) -> Option<&str> {
+        self.rebinding.as_deref()
+    }
+    /// looks for the first key or mouse button pressed since the last frame, and binds it to the action given to `start_rebind`
+    /// modifiers alone are not captured (Ctrl then S gives `Ctrl+S`), and Escape without modifiers cancels the capture
+    /// returns the new binding once it's done
+    pub fn poll_rebind(&mut self, keyboard: &NiceKeyboard) -> Option<Binding> {
+        let action = self.rebinding.clone()?;
+        let binding = keyboard.events.iter().find_map(|event| match event {
+            InputEvent::KeyPressed {
+                key: WinitKey::Code(code),
+                repeat: false,
+                ..
+            } if !is_modifier(*code) => Some(Binding::Key(Chord {
+                key: *code,
+                modifiers: keyboard.modifiers,
+            })),
+            InputEvent::MousePressed(button) => Some(Binding::Mouse {
+                button: *button,
+                modifiers: keyboard.modifiers,
+            }),
+            _ => None,
+        })?;
+        self.rebinding = None;
+        if binding == Binding::Key(Chord::key(KeyCode::Escape)) {
+            return None;
+        }
+        self.clear_action(&action);
+        self.bind(&action, binding);
+        Some(binding)
+    }
+    /// returns the actions (other than `except`) that already use this binding
+    /// useful to warn the player after a rebind
+    pub fn actions_using(&self, binding: Binding, except: Option<&str>) -> Vec<&str> {
+        self.actions
+            .iter()
+            .filter(|f| Some(f.0.as_str()) != except && f.1.contains(&binding))
+            .map(|f| f.0.as_str())
+            .collect()
+    }
+    /// returns the context of an action: the one given to `set_context`, or else `"ui"` for actions starting with `ui_` & `"game"` for the others
+    /// actions of different contexts are never used at the same time (menus vs. moving around), so they can share bindings
+    pub fn context<'a>(&'a self, action: &str) -> &'a str {
+        match self.contexts.iter().find(|f| f.0 == action) {
+            Some((_, context)) => context,
+            None if action.starts_with("ui_") => "ui",
+            None => "game",
+        }
+    }
+    /// puts an action in a context (see `context`)
+    /// contexts aren't saved in files, so actions loaded back get the one from their name
+    pub fn set_context(&mut self, action: &str, context: &str) {
+        match self.contexts.iter_mut().find(|f| f.0 == action) {
+            Some(f) => f.1 = context.to_owned(),
+            None => self.contexts.push((action.to_owned(), context.to_owned())),
+        }
+    }
+    /// returns every binding that triggers more than one action of the same context, with these actions
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<&str>)> {
+        let mut conflicts: Vec<(Binding, Vec<&str>)> = Vec::new();
+        for (action, bindings) in &self.actions {
+            let context = self.context(action);
+            for binding in bindings {
+                if conflicts
+                    .iter()
+                    .any(|f| f.0 == *binding && self.context(f.1[0]) == context)
+                {
+                    continue;
+                }
+                let actions: Vec<&str> = self
+                    .actions_using(*binding, None)
+                    .into_iter()
+                    .filter(|f| self.context(f) == context)
+                    .collect();
+                if actions.len() > 1 {
+                    conflicts.push((*binding, actions));
+                }
+            }
+        }
+        conflicts
+    }
+    /// loads a map from a file (see the module's documentation for the format)
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+    /// saves the map to a file, in a format `load` can read back
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+// modifier keys can't be bound alone when capturing, since they are part of the chord
+fn is_modifier(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::SuperLeft
+            | KeyCode::SuperRight
+    )
+}
+impl FromStr for InputMap {
+    type Err = InputMapParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = InputMap::new();
+        for (i, line) in s.lines().enumerate() {
+            let error = |kind| InputMapParseError { line: i + 1, kind };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (action, bindings) = line
+                .split_once('=')
+                .ok_or_else(|| error(InputMapParseErrorKind::MissingEquals))?;
+            let action = action.trim();
+            if action.is_empty() {
+                return Err(error(InputMapParseErrorKind::MissingAction));
+            }
+            // the action exists even if it has no bindings
+            if map.find(action).is_none() {
+                map.actions.push((action.to_owned(), Vec::new()));
+            }
+            for binding in bindings
+                .split(',')
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+            {
+                let binding = binding
+                    .parse()
+                    .map_err(|e| error(InputMapParseErrorKind::Binding(e)))?;
+                map.bind(action, binding);
+            }
+        }
+        Ok(map)
+    }
+}
+impl Display for InputMap {
+    /// writes the map in the file format
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (action, bindings) in &self.actions {
+            write!(f, "{} =", action)?;
+            for (i, binding) in bindings.iter().enumerate() {
+                write!(f, "{}{}", if i == 0 { " " } else { ", " }, binding)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+/// The vi-keys scheme, as in nethack
+pub const VI_KEYS: &[(&str, &[&str])] = &[
+    ("move_n", &["K"]),
+    ("move_s", &["J"]),
+    ("move_w", &["H"]),
+    ("move_e", &["L"]),
+    ("move_nw", &["Y"]),
+    ("move_ne", &["U"]),
+    ("move_sw", &["B"]),
+    ("move_se", &["N"]),
+    ("run_n", &["Shift+K"]),
+    ("run_s", &["Shift+J"]),
+    ("run_w", &["Shift+H"]),
+    ("run_e", &["Shift+L"]),
+    ("wait", &["Period"]),
+    ("pickup", &["Comma"]),
+    ("ui_select", &["Enter"]),
+    ("ui_up", &["ArrowUp"]),
+    ("ui_down", &["ArrowDown"]),
+    ("ui_cancel", &["Escape"]),
+];
+/// The numpad scheme
+/// the arrows both move & go through menus, which isn't a conflict since `ui_` actions have their own context
+pub const NUMPAD: &[(&str, &[&str])] = &[
+    ("move_n", &["Numpad8", "ArrowUp"]),
+    ("move_s", &["Numpad2", "ArrowDown"]),
+    ("move_w", &["Numpad4", "ArrowLeft"]),
+    ("move_e", &["Numpad6", "ArrowRight"]),
+    ("move_nw", &["Numpad7", "Home"]),
+    ("move_ne", &["Numpad9", "PageUp"]),
+    ("move_sw", &["Numpad1", "End"]),
+    ("move_se", &["Numpad3", "PageDown"]),
+    ("run_n", &["Shift+Numpad8", "Shift+ArrowUp"]),
+    ("run_s", &["Shift+Numpad2", "Shift+ArrowDown"]),
+    ("run_w", &["Shift+Numpad4", "Shift+ArrowLeft"]),
+    ("run_e", &["Shift+Numpad6", "Shift+ArrowRight"]),
+    ("wait", &["Numpad5"]),
+    ("pickup", &["G"]),
+    ("ui_select", &["Enter", "NumpadEnter"]),
+    ("ui_up", &["ArrowUp"]),
+    ("ui_down", &["ArrowDown"]),
+    ("ui_cancel", &["Escape"]),
+];
+/// The WASD scheme, with QEZC for diagonals
+pub const WASD: &[(&str, &[&str])] = &[
+    ("move_n", &["W"]),
+    ("move_s", &["S"]),
+    ("move_w", &["A"]),
+    ("move_e", &["D"]),
+    ("move_nw", &["Q"]),
+    ("move_ne", &["E"]),
+    ("move_sw", &["Z"]),
+    ("move_se", &["C"]),
+    ("run_n", &["Shift+W"]),
+    ("run_s", &["Shift+S"]),
+    ("run_w", &["Shift+A"]),
+    ("run_e", &["Shift+D"]),
+    ("wait", &["Space"]),
+    ("pickup", &["G"]),
+    ("ui_select", &["Enter"]),
+    ("ui_up", &["ArrowUp"]),
+    ("ui_down", &["ArrowDown"]),
+    ("ui_cancel", &["Escape"]),
+];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::logical_key;
+
+    const FILE: &str = "# comments start with a #
+move_n = K, ArrowUp, Numpad8 # until the end of the line
+
+save = Ctrl+S
+ui_select = Enter, Shift+MouseRight, Mouse7
+unbound =
+";
+    fn press(keyboard: &mut NiceKeyboard, key: KeyCode) {
+        keyboard.handle(InputEvent::KeyPressed {
+            key: WinitKey::Code(key),
+            logical: logical_key(key, false).0,
+            text: None,
+            repeat: false,
+        });
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let map: InputMap = FILE.parse().unwrap();
+        let text = map.to_string();
+        assert_eq!(
+            text,
+            "move_n = K, ArrowUp, Numpad8
+save = Ctrl+S
+ui_select = Enter, Shift+MouseRight, Mouse7
+unbound =
+"
+        );
+        assert_eq!(text.parse::<InputMap>().unwrap().to_string(), text);
+        assert_eq!(
+            map.bindings("ui_select")[1],
+            Binding::Mouse {
+                button: WinitMouseButton::Right,
+                modifiers: Modifiers {
+                    shift: true,
+                    ..Default::default()
+                }
+            }
+        );
+        assert!(map.bindings("unbound").is_empty());
+        assert_eq!(map.actions().count(), 4);
+    }
+    #[test]
+    fn bad_files_say_where() {
+        let error = |text: &str| text.parse::<InputMap>().unwrap_err();
+        assert_eq!(
+            error("move_n K"),
+            InputMapParseError {
+                line: 1,
+                kind: InputMapParseErrorKind::MissingEquals
+            }
+        );
+        assert_eq!(error("# nothing\n = K").line, 2);
+        assert_eq!(
+            error("save = Ctrl+Nope").kind,
+            InputMapParseErrorKind::Binding(ChordParseError::UnknownKey("Nope".to_owned()))
+        );
+    }
+    #[test]
+    fn presets_have_no_conflicts() {
+        for map in [InputMap::vi_keys(), InputMap::numpad(), InputMap::wasd()] {
+            assert_eq!(map.conflicts(), Vec::new());
+        }
+    }
+    #[test]
+    fn conflicts_stay_in_their_context() {
+        let mut map = InputMap::numpad();
+        let up: Binding = "ArrowUp".parse().unwrap();
+        // ui_up & move_n share the arrow, but not their context
+        assert_eq!(map.context("ui_up"), "ui");
+        assert_eq!(map.context("move_n"), "game");
+        map.set_context("ui_up", "game");
+        assert_eq!(map.conflicts(), vec![(up, vec!["move_n", "ui_up"])]);
+        map.set_context("ui_up", "ui");
+        map.bind_str("pickup", "Numpad5").unwrap();
+        let five: Binding = "Numpad5".parse().unwrap();
+        assert_eq!(map.conflicts(), vec![(five, vec!["wait", "pickup"])]);
+    }
+    #[test]
+    fn rebinding_takes_the_next_chord() {
+        let mut map = InputMap::wasd();
+        map.start_rebind("pickup");
+        let mut keyboard = NiceKeyboard::new();
+        // nothing was pressed yet
+        assert_eq!(map.poll_rebind(&keyboard), None);
+        assert_eq!(map.rebinding(), Some("pickup"));
+        // modifiers alone aren't bindings
+        keyboard.handle(InputEvent::ModifiersChanged(Modifiers {
+            ctrl: true,
+            ..Default::default()
+        }));
+        press(&mut keyboard, KeyCode::ControlLeft);
+        assert_eq!(map.poll_rebind(&keyboard), None);
+        press(&mut keyboard, KeyCode::KeyP);
+        let ctrl_p: Binding = "Ctrl+P".parse().unwrap();
+        assert_eq!(map.poll_rebind(&keyboard), Some(ctrl_p));
+        assert_eq!(map.bindings("pickup"), &[ctrl_p]);
+        assert_eq!(map.rebinding(), None);
+    }
+    #[test]
+    fn escape_cancels_rebinding() {
+        let mut map = InputMap::wasd();
+        map.start_rebind("pickup");
+        let mut keyboard = NiceKeyboard::new();
+        press(&mut keyboard, KeyCode::Escape);
+        assert_eq!(map.poll_rebind(&keyboard), None);
+        assert_eq!(map.rebinding(), None);
+        assert_eq!(map.bindings("pickup"), &["G".parse::<Binding>().unwrap()]);
+    }
+}
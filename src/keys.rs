@@ -0,0 +1,308 @@
+//! Key names & key combinations ("chords") such as `Ctrl+S` or `Shift+ArrowUp`
+use std::{fmt::Display, str::FromStr};
+
+use winit::keyboard::{KeyCode, NamedKey};
+
+use crate::{Modifiers, NiceKeyboard, WinitKey};
+
+// this generates both the list of key codes and their names, so that they can't get out of sync
+macro_rules! key_codes {
+    ($($name:ident),* $(,)?) => {
+        /// Every key code we know the name of
+        /// the order is fixed (recordings store keys as an index in here), so new keys must be added at the end
+        pub const KEY_CODES: &[KeyCode] = &[$(KeyCode::$name),*];
+        // the names of KEY_CODES, in the same order (they are the same as winit's)
+        const KEY_NAMES: &[&str] = &[$(stringify!($name)),*];
+    };
+}
+key_codes! {
+    KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+    KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    Backquote, Backslash, BracketLeft, BracketRight, Comma, Equal, IntlBackslash,
+    Minus, Period, Quote, Semicolon, Slash,
+    AltLeft, AltRight, Backspace, CapsLock, ContextMenu, ControlLeft, ControlRight,
+    Enter, SuperLeft, SuperRight, ShiftLeft, ShiftRight, Space, Tab,
+    Delete, End, Home, Insert, PageDown, PageUp,
+    ArrowDown, ArrowLeft, ArrowRight, ArrowUp,
+    NumLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7,
+    Numpad8, Numpad9, NumpadAdd, NumpadDecimal, NumpadDivide, NumpadEnter, NumpadEqual,
+    NumpadMultiply, NumpadSubtract,
+    Escape, PrintScreen, ScrollLock, Pause,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+}
+// same thing, but for the logical keys of winit (only the ones that matter for games, the rest become unidentified when recorded)
+macro_rules! named_keys {
+    ($($name:ident),* $(,)?) => {
+        /// Every named logical key we know about
+        /// like `KEY_CODES`, the order is fixed since recordings store an index in here
+        pub const NAMED_KEYS: &[NamedKey] = &[$(NamedKey::$name),*];
+    };
+}
+named_keys! {
+    Alt, AltGraph, CapsLock, Control, Fn, NumLock, ScrollLock, Shift, Super,
+    Enter, Tab, Space, ArrowDown, ArrowLeft, ArrowRight, ArrowUp,
+    End, Home, PageDown, PageUp, Backspace, Delete, Insert,
+    Escape, ContextMenu, Pause, PrintScreen,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+}
+// shorter names people tend to write in config files
+const ALIASES: &[(&str, KeyCode)] = &[
+    ("up", KeyCode::ArrowUp),
+    ("down", KeyCode::ArrowDown),
+    ("left", KeyCode::ArrowLeft),
+    ("right", KeyCode::ArrowRight),
+    ("esc", KeyCode::Escape),
+    ("return", KeyCode::Enter),
+    ("del", KeyCode::Delete),
+    ("pgup", KeyCode::PageUp),
+    ("pgdn", KeyCode::PageDown),
+];
+/// Finds a key code from its name, case-insensitive
+/// accepts winit's names (`ArrowUp`, `Numpad8`, `KeyS`...), single letters & digits (`S`, `4`) and a few aliases (`Up`, `Esc`...)
+pub fn key_code_from_name(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        // single letters & digits are the KeyX & DigitX keys
+        if ch.is_ascii_alphabetic() {
+            return key_code_from_name(&format!("Key{}", ch.to_ascii_uppercase()));
+        }
+        if ch.is_ascii_digit() {
+            return key_code_from_name(&format!("Digit{}", ch));
+        }
+    }
+    KEY_NAMES
+        .iter()
+        .position(|f| f.eq_ignore_ascii_case(name))
+        .map(|i| KEY_CODES[i])
+        .or_else(|| {
+            ALIASES
+                .iter()
+                .find(|f| f.0.eq_ignore_ascii_case(name))
+                .map(|f| f.1)
+        })
+}
+/// The name of a key code, as understood by `key_code_from_name`
+/// letters & digits are written without their prefix (`S` instead of `KeyS`)
+pub fn key_code_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    match (name.strip_prefix("Key"), name.strip_prefix("Digit")) {
+        (Some(letter), _) if letter.len() == 1 => letter.to_owned(),
+        (_, Some(digit)) if digit.len() == 1 => digit.to_owned(),
+        _ => name,
+    }
+}
+/// A key combined with modifiers, such as `Ctrl+S`
+/// It matches only if exactly these modifiers are held (`Ctrl+S` doesn't match Ctrl+Shift+S)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub key: KeyCode,
+    pub modifiers: Modifiers,
+}
+impl Chord {
+    /// a chord without modifiers
+    pub fn key(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifiers: Modifiers::default(),
+        }
+    }
+    /// returns true if the key is held with exactly these modifiers
+    pub fn is_pressed(&self, keyboard: &NiceKeyboard) -> bool {
+        self.modifiers_match(keyboard.modifiers)
+            && keyboard.keys.contains(&WinitKey::Code(self.key))
+    }
+    /// returns true if the key was pressed since the last frame with exactly these modifiers held
+    pub fn is_just_pressed(&self, keyboard: &NiceKeyboard) -> bool {
+        self.modifiers_match(keyboard.modifiers)
+            && keyboard
+                .keys_just_pressed
+                .contains(&WinitKey::Code(self.key))
+    }
+    /// returns true if the key was pressed since the last frame or repeated this frame (see `NiceKeyboard::keys_repeated`), with exactly these modifiers held
+    pub fn is_repeated(&self, keyboard: &NiceKeyboard) -> bool {
+        self.modifiers_match(keyboard.modifiers) && keyboard.is_repeated(WinitKey::Code(self.key))
+    }
+    // the held modifiers are the chord's, leaving out the one its own key sets (holding ShiftLeft always holds Shift)
+    fn modifiers_match(&self, mut held: Modifiers) -> bool {
+        let mut modifiers = self.modifiers;
+        match self.key {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => {
+                (held.shift, modifiers.shift) = (false, false)
+            }
+            KeyCode::ControlLeft | KeyCode::ControlRight => {
+                (held.ctrl, modifiers.ctrl) = (false, false)
+            }
+            KeyCode::AltLeft | KeyCode::AltRight => (held.alt, modifiers.alt) = (false, false),
+            KeyCode::SuperLeft | KeyCode::SuperRight => {
+                (held.super_key, modifiers.super_key) = (false, false)
+            }
+            _ => {}
+        }
+        held == modifiers
+    }
+}
+/// Why a chord couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChordParseError {
+    /// the string was empty (or only had modifiers)
+    MissingKey,
+    /// we don't know this key (or this modifier, if it isn't the last part)
+    UnknownKey(String),
+}
+impl Display for ChordParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChordParseError::MissingKey => write!(f, "missing key"),
+            ChordParseError::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+        }
+    }
+}
+impl std::error::Error for ChordParseError {}
+/// Splits `Ctrl+Shift+S`-like strings into the modifiers & the last part (`S`), which isn't checked
+pub fn split_modifiers(s: &str) -> Result<(Modifiers, &str), ChordParseError> {
+    let mut parts: Vec<&str> = s.split('+').map(|f| f.trim()).collect();
+    let key = parts
+        .pop()
+        .filter(|f| !f.is_empty())
+        .ok_or(ChordParseError::MissingKey)?;
+    let mut modifiers = Modifiers::default();
+    for part in parts {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers.ctrl = true,
+            "shift" => modifiers.shift = true,
+            "alt" | "option" => modifiers.alt = true,
+            "super" | "logo" | "win" | "cmd" | "meta" => modifiers.super_key = true,
+            _ => return Err(ChordParseError::UnknownKey(part.to_owned())),
+        }
+    }
+    Ok((modifiers, key))
+}
+impl FromStr for Chord {
+    type Err = ChordParseError;
+    /// parses `Ctrl+Shift+S`-like strings, the key is the last part and every other part is a modifier
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = split_modifiers(s)?;
+        let key =
+            key_code_from_name(key).ok_or_else(|| ChordParseError::UnknownKey(key.to_owned()))?;
+        Ok(Self { key, modifiers })
+    }
+}
+impl Display for Modifiers {
+    /// writes the held modifiers as a `Ctrl+Shift+` prefix (nothing if none are held)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.super_key {
+            write!(f, "Super+")?;
+        }
+        Ok(())
+    }
+}
+impl Display for Chord {
+    /// writes the chord in the same format `from_str` reads
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.modifiers, key_code_name(self.key))
+    }
+}
+// chords are saved as the strings people write them as (`"Ctrl+S"`), so that config files stay readable
+#[cfg(feature = "serde")]
+impl serde::Serialize for Chord {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Chord {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: Modifiers = Modifiers {
+        shift: false,
+        ctrl: true,
+        alt: false,
+        super_key: false,
+    };
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..CTRL
+    };
+
+    #[test]
+    fn chords_round_trip() {
+        let chord: Chord = "ctrl + shift + s".parse().unwrap();
+        assert_eq!(
+            chord,
+            Chord {
+                key: KeyCode::KeyS,
+                modifiers: CTRL_SHIFT
+            }
+        );
+        assert_eq!(chord.to_string(), "Ctrl+Shift+S");
+        // aliases are written with winit's names
+        assert_eq!(
+            "Cmd+Up".parse::<Chord>().unwrap().to_string(),
+            "Super+ArrowUp"
+        );
+        for &key in KEY_CODES {
+            let chord = Chord {
+                key,
+                modifiers: CTRL,
+            };
+            assert_eq!(chord.to_string().parse(), Ok(chord));
+        }
+    }
+    #[test]
+    fn bad_chords_are_errors() {
+        assert_eq!("".parse::<Chord>(), Err(ChordParseError::MissingKey));
+        assert_eq!("Ctrl+".parse::<Chord>(), Err(ChordParseError::MissingKey));
+        assert_eq!(
+            "Ctrl+Nope".parse::<Chord>(),
+            Err(ChordParseError::UnknownKey("Nope".to_owned()))
+        );
+        assert_eq!(
+            "Hyper+S".parse::<Chord>(),
+            Err(ChordParseError::UnknownKey("Hyper".to_owned()))
+        );
+    }
+    #[test]
+    fn modifiers_must_match_exactly() {
+        let save: Chord = "Ctrl+S".parse().unwrap();
+        assert!(save.modifiers_match(CTRL));
+        assert!(!save.modifiers_match(CTRL_SHIFT));
+        assert!(!save.modifiers_match(Modifiers::default()));
+    }
+    #[test]
+    fn modifier_keys_hold_their_own_modifier() {
+        // holding ShiftLeft holds Shift, which doesn't stop the chord from matching
+        let shift = Chord::key(KeyCode::ShiftRight);
+        assert!(shift.modifiers_match(Modifiers::default()));
+        assert!(shift.modifiers_match(Modifiers {
+            shift: true,
+            ..Default::default()
+        }));
+        assert!(!shift.modifiers_match(CTRL_SHIFT));
+        // whether the chord says it or not
+        let ctrl_shift: Chord = "Ctrl+Shift+ShiftLeft".parse().unwrap();
+        assert!(ctrl_shift.modifiers_match(CTRL_SHIFT));
+        assert!(ctrl_shift.modifiers_match(CTRL));
+        let ctrl: Chord = "Ctrl+ControlLeft".parse().unwrap();
+        assert!(ctrl.modifiers_match(CTRL));
+        assert!(ctrl.modifiers_match(Modifiers::default()));
+    }
+}
@@ -18,108 +18,58 @@
 //!     }
 //! }
 
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use bytemuck::Zeroable;
 use colors::{BLACK, CYAN, GREEN, RED, TRANSPARENT, WHITE, YELLOW};
-use image::{DynamicImage, ImageBuffer, Rgba};
+use image::{DynamicImage, GenericImageView, Rgba};
+use clip::ClipRecorder;
+use grid_recording::GridRecorder;
+use image_layer::ImageLayer;
+use post_process::PostEffect;
+use replay::{InputRecording, InputReplay};
 use ui::{BorderStyle, Button, FillStyle, Label, UIBox, UIData, UIDataEntry, UINode};
-use wgpu::{util::DeviceExt, TextureUsages};
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
-    event::{ElementState, MouseButton},
+    event::{ElementState, MouseButton, MouseScrollDelta},
     event_loop::EventLoop,
     keyboard::PhysicalKey,
     window::{Window as WinitWindow, WindowAttributes},
 };
+pub mod ansi;
+pub mod ascii_art;
+mod binary;
+pub mod clip;
 pub mod colors;
+pub mod export;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "gpu")]
+use gpu::Gpu;
+// without the `gpu` feature, there is never a GPU
+#[cfg(not(feature = "gpu"))]
+type Gpu<'a> = std::marker::PhantomData<&'a ()>;
+pub mod grid_recording;
+pub mod harness;
+pub mod image_layer;
+pub mod input_map;
+pub mod keys;
+pub mod post_process;
+pub mod raster;
+pub mod replay;
+pub mod rexpaint;
+pub mod snapshot;
+// sockets & threads aren't there on the web
+#[cfg(not(target_arch = "wasm32"))]
+pub mod telnet;
+// terminals need a process & a tty
+#[cfg(not(target_arch = "wasm32"))]
+pub mod terminal;
 pub mod ui;
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-struct Vertex {
-    position: [f32; 3],
-    uv: [f32; 2],
-}
-unsafe impl bytemuck::Pod for Vertex {}
-unsafe impl bytemuck::Zeroable for Vertex {}
-/// These are the vertices that occupy the whole screen
-const VERTICES: &[Vertex] = &[
-    // tri 1
-    Vertex {
-        position: [-1., -1., 0.],
-        uv: [0., 0. + 1.],
-    },
-    Vertex {
-        position: [-1., 1., 0.],
-        uv: [0., 1. - 1.],
-    },
-    Vertex {
-        position: [1., 1., 0.],
-        uv: [1., 1. - 1.],
-    },
-    // tri 2
-    Vertex {
-        position: [-1., -1., 0.],
-        uv: [0., 0. + 1.],
-    },
-    Vertex {
-        position: [1., -1., 0.],
-        uv: [1., 0. + 1.],
-    },
-    Vertex {
-        position: [1., 1., 0.],
-        uv: [1., 1. - 1.],
-    },
-];
-/// These are the vertices for individual floating letters
-const VERTICES_I: &[Vertex] = &[
-    // tri 1
-    Vertex {
-        position: [0., 0., 0.],
-        uv: [0., 0. + 1.],
-    },
-    Vertex {
-        position: [0., 1., 0.],
-        uv: [0., 1. - 1.],
-    },
-    Vertex {
-        position: [1., 1., 0.],
-        uv: [1., 1. - 1.],
-    },
-    // tri 2
-    Vertex {
-        position: [0., 0., 0.],
-        uv: [0., 0. + 1.],
-    },
-    Vertex {
-        position: [1., 0., 0.],
-        uv: [1., 0. + 1.],
-    },
-    Vertex {
-        position: [1., 1., 0.],
-        uv: [1., 1. - 1.],
-    },
-];
-// This is the layout of the screen vertices, I mostly copy-pasted it from the wgpu tutorial
-const VERTEX_LAYOUT: wgpu::VertexBufferLayout = /* more copy-pasting :3 */
-    wgpu::VertexBufferLayout {
-        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress, // 1.
-        step_mode: wgpu::VertexStepMode::Vertex,                            // 2.
-        attributes: &[
-            // 3.
-            wgpu::VertexAttribute {
-                offset: 0,                             // 4.
-                shader_location: 0,                    // 5.
-                format: wgpu::VertexFormat::Float32x3, // 6.
-            },
-            wgpu::VertexAttribute {
-                offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress, //3//2
-                shader_location: 1,
-                format: wgpu::VertexFormat::Float32x2, //3
-            },
-        ],
-    };
 /// A font to use in the app. Note that the font must be an image with alpha, where white represents 100% character and transparent 0% character
 /// It must follow the same cp437 grid such as Dwarf Fortress' fonts
 pub enum Font {
@@ -132,6 +82,48 @@ pub enum Font {
     /// (can be used, for instance, if you want to procedurally generate fonts)
     Image(DynamicImage),
 }
+impl Font {
+    /// decodes the image behind the font (it doesn't check that it is a proper cp437 grid)
+    pub fn decode(&self) -> image::ImageResult<DynamicImage> {
+        match self {
+            Font::Image(k) => Ok(k.clone()),
+            Font::Binary(bin) => image::ImageReader::new(std::io::Cursor::new(bin))
+                // with guessed format is important, otherwise `image` wont load those unspecified-format slices of bytes
+                .with_guessed_format()?
+                .decode(),
+            Font::Path(path) => image::ImageReader::open(path)?
+                // it is still important
+                .with_guessed_format()?
+                .decode(),
+        }
+    }
+}
+// decodes every font of the config, panics if one of them can't be loaded
+fn decode_fonts(fonts: &[Font]) -> Vec<DynamicImage> {
+    fonts
+        .iter()
+        .map(|f| f.decode().expect("couldn't load a font"))
+        .collect()
+}
+// does the math to calculate the window size from the config & the fonts
+fn initial_size(config: &Config, images: &[DynamicImage]) -> PhysicalSize<u32> {
+    let char_width = images[0].width() / 16;
+    let char_height = images[0].height() / 16;
+    let pixel_size = (config.size.0 * char_width, config.size.1 * char_height);
+    // PhysicalSize is in screen pixels, so it should more or less ignore DPI (except on the web, where it does *magic*)
+    PhysicalSize::new(
+        (pixel_size.0 + config.padding.0) * config.scale.0,
+        (pixel_size.1 + config.padding.1) * config.scale.1,
+    )
+}
+// the size to ask winit for, with dpi it's in logical pixels, which winit multiplies by the scale factor of the monitor
+fn requested_size(config: &Config, size: PhysicalSize<u32>) -> winit::dpi::Size {
+    if config.dpi {
+        winit::dpi::LogicalSize::new(size.width as f64, size.height as f64).into()
+    } else {
+        size.into()
+    }
+}
 impl Default for Font {
     /// the default font is a variant of comic sans taken from https://dtinth.github.io/comic-mono-font/ but passed trough here http://mifki.com/df/fontgen/ to generate the grid
     fn default() -> Self {
@@ -142,45 +134,59 @@ impl Default for Font {
 /// The window type, with which you do rendering with
 pub struct Window<'a> {
     // Winit's window, I probably could have had messed with lifetimes to make it work (it needs to be dropped after everything that depends on it) but I instead choose to just `Box::leak` it.
-    window: &'static WinitWindow,
+    // it's None for headless windows (see `Window::headless`)
+    window: Option<&'static WinitWindow>,
+    // Everything that lives on the GPU; it's an option since we drop it before rebuilding it after a device loss
+    gpu: Option<Gpu<'a>>,
+    // draws the software renderer's frames in the window, when there is no GPU
+    presenter: Option<raster::Presenter>,
     // All these parameters are explained in the new_inner function
-    surface: wgpu::Surface<'a>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
     char_width: u32,
     char_height: u32,
     buffer_colors_fg: Vec<u8>,
     buffer_colors_bg: Vec<u8>,
     buffer_chars: Vec<u8>,
     set_buffer: Vec<u8>,
-    set_texture: wgpu::Texture,
-    render_pipeline: wgpu::RenderPipeline,
-    bind_group: wgpu::BindGroup,
-    vertex_buffer: wgpu::Buffer,
-    char_grid_texture: wgpu::Texture,
-    fg_texture: wgpu::Texture,
-    bg_texture: wgpu::Texture,
     background_color: (u8, u8, u8, u8),
     config_chargrid: Config,
     dirty: bool,
-    char_grid_size: wgpu::Extent3d,
-    text_texture: wgpu::Texture,
     instances: Vec<InstanceData>,
-    instance_buffer: wgpu::Buffer,
     instance_count: u32,
-    instance_vertices: wgpu::Buffer,
-    instance_pipeline: wgpu::RenderPipeline,
-    surface_conf: wgpu::SurfaceConfiguration,
+    // the decoded fonts, we keep them around so that we can re-upload them if the GPU goes away
+    images: Vec<DynamicImage>,
+    // the size the GPU ressources were created for (the shaders have it baked in)
+    size: PhysicalSize<u32>,
+    // the size of the window right now, what was rendered at `size` gets stretched to it
+    surface_size: PhysicalSize<u32>,
+    // the scale factor of the monitor the window is on (1 for headless windows)
+    scale_factor: f64,
+    // the clip being recorded, if any
+    clip: Option<ClipRecorder>,
+    // the grid recording, if any (see `Config::record_grid`)
+    grid_recorder: Option<GridRecorder>,
+    // the image behind the grid & the ones on top of everything (see the `image_layer` module)
+    background_image: Option<ImageLayer>,
+    overlays: Vec<ImageLayer>,
+    // the post-processing chain, in order (see the `post_process` module)
+    post_effects: Vec<PostEffect>,
+    // when the window was created, the effects' time starts there
+    #[cfg(feature = "gpu")]
+    start_time: f64,
+    // when we last tried to get a GPU back (in ms, see `draw`)
+    #[cfg(feature = "gpu")]
+    last_gpu_retry: f64,
 }
+// how long (in ms) a window without a renderer waits between two tries at getting a GPU
+#[cfg(feature = "gpu")]
+const GPU_RETRY_INTERVAL: f64 = 1000.;
 impl<'a> Window<'a> {
-    async fn new_inner(
+    // creates everything that lives CPU-side, the GPU part is left empty
+    fn new_cpu(
         config: Config,
         size: PhysicalSize<u32>,
-        window: &'static WinitWindow,
-        images: &Vec<DynamicImage>,
+        window: Option<&'static WinitWindow>,
+        images: Vec<DynamicImage>,
     ) -> Self {
-        // save the padding here (define an alias)
-        let padding = config.padding;
         // create the buffers's vecs that we will keep CPU-side and modify, then send to the GPU each frame
         let buffer_colors_fg = vec![0; (config.size.0 * config.size.1) as usize * 4];
         let buffer_colors_bg = vec![0; (config.size.0 * config.size.1) as usize * 4];
@@ -191,590 +197,78 @@ impl<'a> Window<'a> {
         // Calculate the size of chars based on the assumption that the fonts are 256-character grids following cp437 encoding
         let char_width = images[0].width() / 16;
         let char_height = images[0].height() / 16;
-        // define more aliases
-        let cg_width = config.size.0;
-        let cg_height = config.size.1;
-        // calculate padding (this is magic math I could have had figured out properly but I instead fiddled with it till it worked).
-        let size_x = (size.width - config.padding.0 * config.scale.0) as f32 / (size.width) as f32;
-        let size_y =
-            (size.height - config.padding.1 * config.scale.1) as f32 / (size.height) as f32;
-
-        // define more aliases
-        let config_chargrid = config;
-        let max_instances = config_chargrid.max_instances;
-
-        // my instance configuration is mainly for wasm32 support
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            #[cfg(not(target_arch = "wasm32"))]
-            backends: wgpu::Backends::PRIMARY,
-            #[cfg(target_arch = "wasm32")]
-            backends: wgpu::Backends::GL,
-
-            ..Default::default()
-        });
-        // create the surface 
-        let surface = instance.create_surface(window).unwrap();
-        // create the adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::None,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-        // create the device and the queue; we will use the device when creating ressources and the queue when issuing orders
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    // note more wasm32 things
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    // Mostly useful for debugging
-                    label: Some("device"),
-                    memory_hints: wgpu::MemoryHints::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
-
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter() 
-            .find(|f| {
-                // sRGB changes how colors are scaled, which makes low RGB values brighter
-                if config_chargrid.srgb {
-                    f.is_srgb()
-                } else {
-                    !f.is_srgb()
-                }
-            })
-            .copied()
-            // in case we didn't find anything that worked, we rely on the first element
-            .unwrap_or(surface_caps.formats[0]);
-        // render surface's config
-        let config = wgpu::SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        // we convert all our font images to rgba8
-        let images_rgba8: Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> =
-            images.iter().map(|f| f.to_rgba8()).collect();
-        // we get the dimension of the first one (all font images must be the same size since I am using an array)
-        let dimensions = images_rgba8[0].dimensions();
-        // texture size of the font images on the GPU
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            // note the array layers
-            depth_or_array_layers: images_rgba8.len() as u32,
-        };
-        // creates the GPU-side texture
-        let wgpu_side_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2, 
-            // Format matches image format
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            // Copy DST is important since we dont actually create it with content
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("text texture"),
-            view_formats: &[],
-        });
-        // The size of the chargrid that covers the whole screen (except the padding)
-        let char_grid_size = wgpu::Extent3d {
-            width: cg_width,
-            height: cg_height,
-            depth_or_array_layers: 01,
-        };
-        // We create its texture
-        let char_grid_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: char_grid_size, 
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2, 
-            // It's a texture of single bytes, thus R8Unorm, which provides us with f32s scaled from 0. to 1. on the shader side
-            format: wgpu::TextureFormat::R8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("text grid texture"),
-            view_formats: &[],
-        });
-        // creates the texture which stores foreground colors for each grid character
-        let fg_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: char_grid_size, 
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            // note the format
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("text fg texture"),
-            view_formats: &[],
-        });
-        // creates the texture which stores background colors for each grid character
-
-        let bg_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: char_grid_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("text bg texture"),
-            view_formats: &[],
-        });
-        // creates the texture which stores which font each character uses
-        // it is used in the shader to index into the font texture array
-        let set_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: char_grid_size, 
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            // note that this gives us a limitation of 256 font files
-            format: wgpu::TextureFormat::R8Unorm, 
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("text set texture"),
-            view_formats: &[],
-        });
-        // Generic pixel perfect sampler that clamps to the border
-        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-        // view for the text texture, it is provided to the pipeline when it renders
-        let view = wgpu_side_texture.create_view(&wgpu::TextureViewDescriptor {
-            dimension: Some(wgpu::TextureViewDimension::D2Array),
-            ..Default::default()
-        });
-        // create other views
-        let view_char_grid = char_grid_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let fg_view = fg_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let bg_view = bg_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let set_view = set_texture.create_view(&wgpu::TextureViewDescriptor::default()); //bg//bg
-        for i in images_rgba8.iter().enumerate() {
-            // ensure images are the same size
-            assert_eq!(
-                dimensions,
-                i.1.dimensions(),
-                "images must have the same size, sadly :("
-            );
-            // writes the font texture to the array
-            queue.write_texture(
-                wgpu::ImageCopyTextureBase {
-                    texture: &wgpu_side_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d {
-                        x: 0,
-                        y: 0,
-                        // note the z-offset
-                        z: i.0 as u32,
-                    }, 
-                    aspect: wgpu::TextureAspect::All,
-                },
-                &i.1, 
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * dimensions.0),
-                    rows_per_image: Some(dimensions.1),
-                },
-                wgpu::Extent3d {
-                    width: texture_size.width,
-                    height: texture_size.height,
-                    depth_or_array_layers: 1,
-                },
-            );
-        }
-        // writes the textures with the stored buffers
-        // first, which char the grid uses
-        queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &char_grid_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &buffer_chars,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width),
-                rows_per_image: Some(cg_height),
-            },
-            char_grid_size,
-        );
-        // second, the foreground color
-        queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &fg_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &buffer_colors_fg,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width * 4),
-                rows_per_image: Some(cg_height),
-            },
-            char_grid_size,
-        );
-        // third, the background color
-        queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &bg_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &buffer_colors_bg,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width * 4),
-                rows_per_image: Some(cg_height),
-            },
-            char_grid_size,
-        );
-        // includes the shaders, first the chargrid shader
-        let shader = include_str!("text_shader.wglsl");
-        // then the floating characters/instances shader
-        let mut shader_instance = include_str!("instance_shader.wglsl").to_owned();
-        // I could have went with constants but I instead just pre-filtered the shaders
-        // This is bad practice, but it Just Works
-        shader_instance = shader_instance.replace("$SC_WIDTH", format!("{}", size.width).as_str());
-        shader_instance =
-            shader_instance.replace("$SC_HEIGHT", format!("{}", size.height).as_str());
-        shader_instance = shader_instance.replace("$C_WIDTH", format!("{}", char_width).as_str());
-        shader_instance = shader_instance.replace("$C_HEIGHT", format!("{}", char_height).as_str());
-        shader_instance = shader_instance.replace("$PADDING_X", format!("{}", padding.0).as_str());
-        shader_instance = shader_instance.replace("$PADDING_Y", format!("{}", padding.1).as_str());
-        shader_instance = shader_instance.replace(
-            "$SCALE_FACTOR",
-            format!("{}", cg_width as f32 / (cg_height as f32)).as_str(),
-        );
-        shader_instance =
-            shader_instance.replace("$SCALE_X", format!("{}", config_chargrid.scale.0).as_str());
-        shader_instance =
-            shader_instance.replace("$SCALE_Y", format!("{}", config_chargrid.scale.1).as_str()); //X//0
-        let mut shader = shader.replace(
-            "$SCALE_FACTOR_X",
-            format!("f32({})", dimensions.0 as f32).as_str(),
-        ); 
-        shader = shader.replace(
-            "$SCALE_FACTOR_Y",
-            format!(
-                "f32({})",
-            )
-            .as_str(),
-        );
-        shader = shader.replace(
-            "$CHARGRID",
-            format!("vec2<f32>(f32({}),f32({}))", cg_width, cg_height).as_str(),
-        );
-        // loads the shaders into WGPU
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("text shader"),
-            source: wgpu::ShaderSource::Wgsl(shader.into()),
-        });
-        let instance_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("instance shader"),
-            source: wgpu::ShaderSource::Wgsl(shader_instance.into()),
-        });
-        // Here, I copied more things from the wgpu tutorial; each of these bindings echo textures & samplers from the shaders
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                // nooo i didnt copy paste anythiiing <- this is a lie
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2Array,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 4,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 5, //4
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2, 
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                ],
-                label: Some("text rendering bind group layout"),
-            });
-        // creates the bind group with the layout we just provided
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    // here we actually point it to what we want it to be in the shaders
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&view_char_grid),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::TextureView(&fg_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&bg_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 5,                                             
-                    resource: wgpu::BindingResource::TextureView(&set_view), 
-                },
-            ],
-            label: Some("text rendering bind group"),
-        });
-        // we specify with the texture bind group layout our render pipeline layout
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("text render pipeline layout"),
-                bind_group_layouts: &[&texture_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        // the render pipeline is a vital part of WGPU rendering
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("text render pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                // specify the shader
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[VERTEX_LAYOUT],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                // also specify the shader
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    // blendstates are useful here to blend the config's background color with the color of the chargrid
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent::OVER,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            // no depth
-            depth_stencil: None,
-            multiview: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            cache: None,
-        });
-        // this is the render pipeline that renders floating characters / instances
-        let instance_render_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("text instances render pipeline"),
-                // it uses the same layout
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    // but not the same shader
-                    module: &instance_shader,
-                    entry_point: Some("vs_main"),
-                    // and it has an extra buffer
-                    buffers: &[VERTEX_LAYOUT, INSTANCE_LAYOUT],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    // apart from the shader, the fragment uses the same configuration
-                    module: &instance_shader,
-                    entry_point: Some("fs_main"),
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.format,
-                        blend: Some(wgpu::BlendState {
-                            color: wgpu::BlendComponent {
-                                src_factor: wgpu::BlendFactor::SrcAlpha,
-                                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                                operation: wgpu::BlendOperation::Add,
-                            },
-                            alpha: wgpu::BlendComponent::OVER,
-                        }), 
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Cw,
-                    cull_mode: None,
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                // still no depth testing
-                depth_stencil: None,
-                multiview: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-        });
-        
-        // configure the surface
-        surface.configure(&device, &config);
-        // creates the vertex buffer for the triangles that cover the screen (note that we also do some math here to ensure padding works)
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertex buffer"),
-            contents: bytemuck::cast_slice(
-                &VERTICES
-                    .into_iter()
-                    .map(|f| {
-                        // apply padding
-                        Vertex {
-                            position: [
-                                f.position[0] * size_x,
-                                f.position[1] * size_y,
-                                // we don't care about z-position
-                                f.position[2],
-                            ],
-                            uv: [f.uv[0], f.uv[1]],
-                        }
-                    })
-                    .collect::<Vec<Vertex>>(),
-            ),
-            // we will not write to it, so it doesn't need `COPY_DST`
-            usage: wgpu::BufferUsages::VERTEX,
-        });
         // this is the CPU-side buffer of instances/floating characters
-        let instances = vec![InstanceData::zeroed(); max_instances as usize];
-        // we create its buffer
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("instance buffer"),
-            contents: bytemuck::cast_slice(&instances),
-            // note that in wgpu, instances use `BufferUsages::VERTEX`
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-        });
-        // we create the buffer that contains the vertices
-        let instance_vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("instance vertice buffer"),
-            contents: bytemuck::cast_slice(&VERTICES_I),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let instances = vec![InstanceData::zeroed(); config.max_instances as usize];
         // we create the variable that contains our instance count (the amount of instances that currently have values)
         let instance_count = 0;
+        let grid_recorder = config
+            .record_grid
+            .is_some()
+            .then(|| GridRecorder::new(config.size));
         // we return the completed window
         Self {
-            window, 
-            surface,
-            device,
-            queue,
+            window,
+            gpu: None,
+            presenter: None,
             instances,
             instance_count,
-            instance_buffer,
-            text_texture: wgpu_side_texture,
             buffer_colors_bg,
             buffer_colors_fg,
             buffer_chars,
-            render_pipeline,
-            bind_group: texture_bind_group,
-            vertex_buffer,
-            char_grid_texture,
-            fg_texture,
-            bg_texture,
             background_color,
-            config_chargrid,
-            dirty: false,
-            char_grid_size,
+            config_chargrid: config,
+            // dirty so that the first draw uploads everything
+            dirty: true,
             char_width,
             char_height,
             set_buffer,
-            set_texture,
-            instance_vertices,
-            instance_pipeline: instance_render_pipeline,
-            surface_conf: config,
+            images,
+            size,
+            surface_size: size,
+            scale_factor: 1.,
+            clip: None,
+            grid_recorder,
+            background_image: None,
+            overlays: Vec::new(),
+            post_effects: Vec::new(),
+            #[cfg(feature = "gpu")]
+            start_time: now_ms(),
+            #[cfg(feature = "gpu")]
+            last_gpu_retry: f64::NEG_INFINITY,
+        }
+    }
+    async fn new_inner(
+        config: Config,
+        size: PhysicalSize<u32>,
+        window: &'static WinitWindow,
+        images: &[DynamicImage],
+    ) -> Self {
+        let mut this = Self::new_cpu(config, size, Some(window), images.to_vec());
+        // everything GPU-related happens in there
+        #[cfg(feature = "gpu")]
+        {
+            this.gpu = Gpu::new(&this.config_chargrid, size, window, images).await;
+        }
+        // without a GPU, the software renderer draws in the window instead
+        #[cfg(feature = "software")]
+        if this.gpu.is_none() {
+            this.presenter = raster::Presenter::new(window);
         }
+        assert!(
+            this.gpu.is_some() || this.presenter.is_some(),
+            "couldn't find a GPU to draw with (the `software` feature lets yarl-2 draw without one)"
+        );
+        this.scale_factor = window.scale_factor();
+        // with dpi, the window isn't `size` big (and it may not be anyway if the OS had other plans)
+        this.resize(window.inner_size());
+        this
+    }
+    /// creates a window that only exists in memory: no winit window, no GPU
+    /// drawing does nothing, but everything else works, which is useful for tests (see `harness::TestHarness`) and other backends
+    pub fn headless(config: Config) -> Window<'static> {
+        let images = decode_fonts(&config.font);
+        let size = initial_size(&config, &images);
+        Window::new_cpu(config, size, None, images)
     }
     /// THIS IS THE FUNCTION YOU MUST CALL IF YOU ARE FANCY, BUT YOU CAN ALSO JUST USE `run_game()`
     /// note: leaks memory
@@ -783,49 +277,15 @@ impl<'a> Window<'a> {
         T: Yarl2Game,
     {
         // extracts the images of the config
-        let images: Vec<DynamicImage> = config
-            .font
-            .iter()
-            .map(|f| {
-                match f {
-                    Font::Image(k) => k.clone(),
-                    Font::Binary(bin) => {
-                        image::ImageReader::new(std::io::Cursor::new(bin))
-                            // with guessed format is important, otherwise `image` wont load those unspecified-format slices of bytes
-                            .with_guessed_format()
-                            .unwrap()
-                            .decode()
-                            .unwrap()
-                    }
-                    Font::Path(path) => image::ImageReader::open(path)
-                        .unwrap()
-                        // it is still important
-                        .with_guessed_format()
-                        .unwrap()
-                        .decode()
-                        .unwrap(),
-                }
-            })
-            .collect();
-        // does more math we will re-do later to calculate the window size
-        let char_width = images[0].width() / 16;
-        let char_height = images[0].height() / 16;
-        let pixel_size = (
-            config.size.0 * char_width,  
-            config.size.1 * char_height, 
-        );
+        let images = decode_fonts(&config.font);
+        let size = initial_size(&config, &images);
         let event_loop = EventLoop::new().unwrap(); 
-        // PhysicalSize is in screen pixels, so it should more or less ignore DPI (except on the web, where it does *magic*)
-        let size = PhysicalSize::new(
-            (pixel_size.0 + config.padding.0) * config.scale.0,
-            (pixel_size.1 + config.padding.1) * config.scale.1,
-        ); 
         // creates (and leaks) the window!
         let window: &'static WinitWindow = Box::leak(Box::new(
             event_loop
                 .create_window(
                     WindowAttributes::default()
-                        .with_inner_size(size)
+                        .with_inner_size(requested_size(&config, size))
                         .with_title(&config.name) 
                         // I set it up as resizable, but I haven't really spent time on this part
                         // So it just stretches the original dimensions
@@ -834,13 +294,17 @@ impl<'a> Window<'a> {
                 )
                 .unwrap(),
         ));
+        window.set_ime_allowed(config.ime);
+        if let Some(mode) = config.fullscreen {
+            window.set_fullscreen(winit_fullscreen(window, mode));
+        }
         // Copy-pasted again
         #[cfg(target_arch = "wasm32")]
         {
             // Winit prevents sizing with CSS, so we have to set
             // the size manually when on web.
             use winit::dpi::PhysicalSize;
-            let _ = window.request_inner_size(size); //PhysicalSize::new(450, 400)
+            let _ = window.request_inner_size(requested_size(&config, size)); //PhysicalSize::new(450, 400)
 
             use winit::platform::web::WindowExtWebSys;
             web_sys::window()
@@ -854,207 +318,78 @@ impl<'a> Window<'a> {
                 .expect("Couldn't append canvas to document body.");
         }
 
-        let return_value =
-            {
-                // now, since the default wasm32 target does not support `smol`, we must use a different crate to create all the variables, since they are created by our async function
-                #[cfg(target_arch="wasm32")]
-                {
-                wasm_rs_async_executor::single_threaded::block_on(Window::new_inner(config, size, &window, &images))
-                }
-
-                #[cfg(not(target_arch="wasm32"))]
-                {
-                smol::block_on(Window::new_inner(config, size, &window, &images))
-                }
-            }
-            ; 
+        // we load the replay before anything else so that a bad file fails early
+        let replay = config.replay_input.as_ref().map(|path| {
+            InputReplay::new(InputRecording::load(path).expect("couldn't load the input replay"))
+        });
+        let record_input = config.record_input.clone();
+        let keyboard = NiceKeyboard::for_config(&config);
+        let return_value = block_on(Window::new_inner(config, size, window, &images));
         // wait just calls the receive_event when an event is received
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Wait);
         // this creates the struct that will run the event loop
         let mut event_loop_runner = EventLoopWrapper {
             game,
             window: return_value,
-            keyboard: NiceKeyboard {
-                keys: HashSet::new(),
-                letters: HashSet::new(),
-                mouse_position: (0, 0),
-                mouse_pressed: false,
-            },
+            keyboard,
+            frame: 0,
+            recorder: record_input.map(|path| (InputRecording::new(), path)),
+            replay,
+            clock_offset: -now_ms(),
+            minimized: false,
         };
         // this runs the `event_loop_runner`
         let _ = event_loop.run_app(&mut event_loop_runner);
         // this exits the process if we make it out of the run_app
         std::process::exit(0)
     }
-    /// this function transmits all CPU-side buffers to the GPU
-    /// this is quite an heavy task; it would be a good idea to instead use memory-mapped regions instead of transfering everything
-    fn update(&mut self) {
-        // redefine values for convenience
-        let cg_width = self.config_chargrid.size.0;
-        let cg_height = self.config_chargrid.size.1;
-        self.queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &self.char_grid_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &self.buffer_chars,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width),
-                rows_per_image: Some(cg_height),
-            },
-            self.char_grid_size,
-        );
-        self.queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &self.fg_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &self.buffer_colors_fg,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width * 4),
-                rows_per_image: Some(cg_height),
-            },
-            self.char_grid_size, //self.
-        );
-        self.queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &self.bg_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &self.buffer_colors_bg,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width * 4),
-                rows_per_image: Some(cg_height),
-            },
-            self.char_grid_size,
-        );
-        self.queue.write_texture(
-            wgpu::ImageCopyTextureBase {
-                texture: &self.set_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &self.set_buffer, 
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(cg_width), 
-                rows_per_image: Some(cg_height),
-            },
-            self.char_grid_size,
-        );
-        self.queue.write_buffer(
-            &self.instance_buffer,
-            0,
-            bytemuck::cast_slice(&self.instances),
-        );
+    /// starts recording a clip: from now on, the frames that are drawn are kept (see `clip::ClipRecorder`)
+    /// the frames are kept as grids, and drawn by the software renderer when the clip is saved
+    pub fn start_clip(&mut self) {
+        self.clip = Some(ClipRecorder::new());
     }
-    // this function renders everything to the screen
-    fn draw(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // if *anything* is dirty, we update *everything*
-        // this is inefficient and should be improved
-        if self.dirty {
-            self.update();
-        }
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("text rendering command encoder"),
-            });
-        // clear render passs
-        // it fills the screen with config's background color
-        {
-            // setups the render pass; for a color clear pass, we dont need anything else
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("clear render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            // note how we turn it to floats (it was originally an u8 tuple)
-                            r: self.background_color.0 as f64 / 255.,
-                            g: self.background_color.1 as f64 / 255., 
-                            b: self.background_color.2 as f64 / 255.,
-                            a: self.background_color.3 as f64 / 255.,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-        }
-        // render text pass
-        // this renders the  character grid
-        {
-            // setup the render pass
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("text render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            // since this render pass needs buffers+a pipeline, we provide them
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            // then we draw, 0..6 vertices since we have two 3-vertices triangles
-            // note the 0..1, since we don't use instances
-            render_pass.draw(0..6, 0..1);
-        }
-        // render instances pass
-        // this renders the instances / floating characters
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("instance render pass"),
-                // copy pasted
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            // sets the data
-            render_pass.set_pipeline(&self.instance_pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]); 
-            render_pass.set_vertex_buffer(0, self.instance_vertices.slice(..));
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            // draws the triangles
-            // note that we now use 0..self.instance_count instead of 0..1, since we now have an instance array
-            render_pass.draw(0..6, 0..self.instance_count);
-        }
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-        Ok(())
+    /// stops recording the clip & returns it (use `ClipRecorder::save_gif` to save it)
+    pub fn stop_clip(&mut self) -> Option<ClipRecorder> {
+        self.clip.take()
+    }
+    /// returns true while a clip is being recorded
+    pub fn is_recording_clip(&self) -> bool {
+        self.clip.is_some()
+    }
+    // gives the frame that was just drawn to the clip, if we are recording one & it wants a frame
+    fn capture_clip_frame(&mut self) {
+        // the clip is taken out so that it can look at the window
+        if let Some(mut clip) = self.clip.take() {
+            clip.add_window_frame(self);
+            self.clip = Some(clip);
+        }
+    }
+    /// starts recording the grid: from now on, what changes in it at each draw is written down (see the `grid_recording` module)
+    /// it restarts the recording if there was one
+    pub fn start_grid_recording(&mut self) {
+        self.grid_recorder = Some(GridRecorder::new(self.config_chargrid.size));
+    }
+    /// stops recording the grid & returns the recording (use `GridRecording::save` to save it)
+    pub fn stop_grid_recording(&mut self) -> Option<grid_recording::GridRecording> {
+        self.grid_recorder.take().map(|recorder| recorder.finish())
+    }
+    /// returns true while the grid is being recorded
+    pub fn is_recording_grid(&self) -> bool {
+        self.grid_recorder.is_some()
+    }
+    // saves the grid recording to `Config::record_grid`, when the app exits
+    fn save_grid_recording(&mut self) {
+        let (Some(path), Some(recording)) = (
+            self.config_chargrid.record_grid.clone(),
+            self.stop_grid_recording(),
+        ) else {
+            return;
+        };
+        if let Err(_e) = recording.save(&path) {
+            // printing breaks wasm32 (and the file system isn't there anyway)
+            #[cfg(not(target_arch = "wasm32"))]
+            eprintln!("couldn't save the grid recording to {}: {}", path, _e);
+        }
     }
     /// sets fg at a point
     pub fn set_fg_at<P>(&mut self, x: P, y: P, fg: Col)
@@ -1082,9 +417,9 @@ impl<'a> Window<'a> {
     where
         P: TryInto<usize>,
     {
-        let k = self.text_texture.size().depth_or_array_layers; //.fg_texture//set_texture
+        let k = self.images.len(); //.fg_texture//set_texture
         assert!(
-            value < k as u8,
+            (value as usize) < k,
             "cannot access set higher than the amounts we have registered"
         ); //be//fg
         if let Ok(x) = x.try_into() {
@@ -1232,6 +567,74 @@ impl<'a> Window<'a> {
         self.set_buffer.fill(0);
         self.instance_count = 0;
     }
+    /// returns the size of the grid, in cells
+    pub fn grid_size(&self) -> (u32, u32) {
+        self.config_chargrid.size
+    }
+    // returns the index of a cell in the buffers (multiply it by 4 for the colors), None if it's out of the grid
+    fn cell_index<P>(&self, x: P, y: P) -> Option<usize>
+    where
+        P: TryInto<usize>,
+    {
+        let x = x.try_into().ok()?;
+        let y = y.try_into().ok()?;
+        if x < self.config_chargrid.size.0 as usize && y < self.config_chargrid.size.1 as usize {
+            Some(x + y * self.config_chargrid.size.0 as usize)
+        } else {
+            None
+        }
+    }
+    /// returns the cp437 glyph at a point, None if it's out of the grid
+    pub fn char_at_bin<P>(&self, x: P, y: P) -> Option<u8>
+    where
+        P: TryInto<usize>,
+    {
+        self.cell_index(x, y).map(|index| self.buffer_chars[index])
+    }
+    /// returns the char at a point (decoded from cp437), None if it's out of the grid
+    pub fn char_at<P>(&self, x: P, y: P) -> Option<char>
+    where
+        P: TryInto<usize>,
+    {
+        self.char_at_bin(x, y).map(u8_to_ch)
+    }
+    /// returns the fg at a point, None if it's out of the grid
+    pub fn fg_at<P>(&self, x: P, y: P) -> Option<Col>
+    where
+        P: TryInto<usize>,
+    {
+        self.cell_index(x, y).map(|index| {
+            let n = &self.buffer_colors_fg[index * 4..index * 4 + 4];
+            (n[0], n[1], n[2], n[3])
+        })
+    }
+    /// returns the bg at a point, None if it's out of the grid
+    pub fn bg_at<P>(&self, x: P, y: P) -> Option<Col>
+    where
+        P: TryInto<usize>,
+    {
+        self.cell_index(x, y).map(|index| {
+            let n = &self.buffer_colors_bg[index * 4..index * 4 + 4];
+            (n[0], n[1], n[2], n[3])
+        })
+    }
+    /// returns the set/font at a point, None if it's out of the grid
+    pub fn set_at<P>(&self, x: P, y: P) -> Option<u8>
+    where
+        P: TryInto<usize>,
+    {
+        self.cell_index(x, y).map(|index| self.set_buffer[index])
+    }
+    /// reads `len` chars on a line, starting at a point (it stops at the edge of the grid)
+    pub fn text_at(&self, x: u32, y: u32, len: usize) -> String {
+        (x..x.saturating_add(len as u32))
+            .map_while(|x| self.char_at(x, y))
+            .collect()
+    }
+    /// returns the instances that were added since the last clear
+    pub fn instances(&self) -> &[InstanceData] {
+        &self.instances[..self.instance_count as usize]
+    }
     /// will return false if couldn't add the instance due to having exceeded the limit
     /// if it returned true, that means the instance was added, and dirty will be flagged (and we will resend everything!)
     pub fn add_instance(&mut self, mut instance: InstanceData) -> bool {
@@ -1245,12 +648,52 @@ impl<'a> Window<'a> {
             false
         }
     }
-    /// panics if anything is out of bounds
+    /// sets (or removes, with None) the image drawn behind the grid, which shows through the cells with a transparent bg
+    pub fn set_background_image(&mut self, image: Option<ImageLayer>) {
+        self.background_image = image;
+    }
+    /// returns the image drawn behind the grid, to move it around for instance
+    pub fn background_image_mut(&mut self) -> Option<&mut ImageLayer> {
+        self.background_image.as_mut()
+    }
+    /// adds an image on top of everything (the grid & the instances), over the ones that were added before it
+    /// returns its index in `overlays_mut`
+    pub fn add_overlay(&mut self, image: ImageLayer) -> usize {
+        self.overlays.push(image);
+        self.overlays.len() - 1
+    }
+    /// the images on top of everything, from the bottom one to the top one
+    pub fn overlays_mut(&mut self) -> &mut Vec<ImageLayer> {
+        &mut self.overlays
+    }
+    /// removes every image on top of everything
+    pub fn clear_overlays(&mut self) {
+        self.overlays.clear();
+    }
+    /// adds a post effect at the end of the chain, it runs on what the effects before it made
+    /// returns its index in `post_effects_mut`
+    pub fn add_post_effect(&mut self, effect: PostEffect) -> usize {
+        self.post_effects.push(effect);
+        self.post_effects.len() - 1
+    }
+    /// the post effects, in the order they run (to change their params for instance)
+    pub fn post_effects_mut(&mut self) -> &mut Vec<PostEffect> {
+        &mut self.post_effects
+    }
+    /// removes every post effect, the frame goes straight to the window again
+    pub fn clear_post_effects(&mut self) {
+        self.post_effects.clear();
+    }
+    /// panics if anything is out of bounds (a snapshot can go up to the last row & column, so `take_snapshot(0, 0, w, h)` takes the whole grid)
     pub fn take_snapshot(&self, x: u32, y: u32, width: u32, height: u32) -> Snapshot {
         let w = self.config_chargrid.size.0;
         let h = self.config_chargrid.size.1;
-        if x + width >= w || y + height >= h {
-            panic!("out of bound! {}> {w} or {} > {h}", x + width, y + height);
+        if x as u64 + width as u64 > w as u64 || y as u64 + height as u64 > h as u64 {
+            panic!(
+                "out of bound! {} > {w} or {} > {h}",
+                x as u64 + width as u64,
+                y as u64 + height as u64
+            );
         }
         let mut s = Snapshot {
             begin: (x, y),
@@ -1278,6 +721,7 @@ impl<'a> Window<'a> {
         s
     }
     /// Write a snapshot at a point
+    /// every cell is copied, even the transparent ones (see `blit_snapshot`)
     pub fn apply_snapshot(&mut self, snapshot: &Snapshot, x: i32, y: i32) {
         let bx = x;
         let by = y;
@@ -1305,19 +749,250 @@ impl<'a> Window<'a> {
             }
         }
     }
+    /// draws a snapshot at a point like a stamp: cells with the char 0 are skipped, and transparent backgrounds keep the background under them
+    /// (see the `snapshot` module)
+    pub fn blit_snapshot(&mut self, snapshot: &Snapshot, x: i32, y: i32) {
+        for sx in 0..snapshot.size.0 {
+            for sy in 0..snapshot.size.1 {
+                let Some(cell) = snapshot.cell(sx, sy) else {
+                    continue;
+                };
+                if cell.is_transparent() {
+                    continue;
+                }
+                let (x, y) = (x + sx as i32, y + sy as i32);
+                self.set_char_at_bin(x, y, cell.ch);
+                self.set_set_at(x, y, cell.set);
+                self.set_fg_at(x, y, cell.fg);
+                if cell.bg.3 != 0 {
+                    self.set_bg_at(x, y, cell.bg);
+                }
+            }
+        }
+    }
+    /// converts a position in window pixels (like the ones winit gives) to a position in cells
+    /// the fractional part is the position inside the cell
+    pub fn pixel_to_cell(&self, x: f64, y: f64) -> (f32, f32) {
+        // the window stretches what we render at `size`, so we undo the stretching first (this also takes care of dpi)
+        let x = x * self.size.width as f64 / self.surface_size.width.max(1) as f64;
+        let y = y * self.size.height as f64 / self.surface_size.height.max(1) as f64;
+        // the padding gets scaled too
+        let x = x / self.config_chargrid.scale.0 as f64;
+        let y = y / self.config_chargrid.scale.1 as f64;
+        let x = x - self.config_chargrid.padding.0 as f64 / 2.;
+        let y = y - self.config_chargrid.padding.1 as f64 / 2.;
+        let x = x / self.char_width as f64;
+        let y = y / self.char_height as f64;
+        (x as f32, y as f32)
+    }
+    /// the inverse of `pixel_to_cell`: converts a position in cells to a position in window pixels
+    pub fn cell_to_pixel(&self, x: f32, y: f32) -> (f64, f64) {
+        let x = x as f64 * self.char_width as f64 + self.config_chargrid.padding.0 as f64 / 2.;
+        let y = y as f64 * self.char_height as f64 + self.config_chargrid.padding.1 as f64 / 2.;
+        let x = x * self.config_chargrid.scale.0 as f64;
+        let y = y * self.config_chargrid.scale.1 as f64;
+        (
+            x * self.surface_size.width as f64 / self.size.width.max(1) as f64,
+            y * self.surface_size.height as f64 / self.size.height.max(1) as f64,
+        )
+    }
+    /// returns the scale factor of the monitor the window is on (2 on most HiDPI screens, 1 for headless windows)
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+    // the window moved to a monitor with another scale factor, returns the size that keeps the window as big for the user
+    fn rescale(&mut self, scale_factor: f64) -> PhysicalSize<u32> {
+        let ratio = scale_factor / self.scale_factor;
+        self.scale_factor = scale_factor;
+        PhysicalSize::new(
+            (self.surface_size.width as f64 * ratio).round() as u32,
+            (self.surface_size.height as f64 * ratio).round() as u32,
+        )
+    }
+    /// lets the IME compose text (or stops it), see `Config::ime`
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        if let Some(window) = self.window {
+            window.set_ime_allowed(allowed);
+        }
+    }
+    /// tells the IME where the text being typed is (in cells), so that it puts its candidate box right next to it
+    pub fn set_ime_cursor_area(&self, x: i32, y: i32, width: u32) {
+        if let Some(window) = self.window {
+            let start = self.cell_to_pixel(x as f32, y as f32);
+            let end = self.cell_to_pixel((x + width.max(1) as i32) as f32, (y + 1) as f32);
+            window.set_ime_cursor_area(
+                winit::dpi::PhysicalPosition::new(start.0, start.1),
+                winit::dpi::PhysicalSize::new(end.0 - start.0, end.1 - start.1),
+            );
+        }
+    }
+    /// returns the winit window, None if the window is headless
+    /// this is the escape hatch for everything that doesn't have a method here
+    pub fn winit_window(&self) -> Option<&'static WinitWindow> {
+        self.window
+    }
+    /// goes fullscreen (or back to a window with None)
+    pub fn set_fullscreen(&self, mode: Option<FullscreenMode>) {
+        if let Some(window) = self.window {
+            window.set_fullscreen(mode.and_then(|mode| winit_fullscreen(window, mode)));
+        }
+    }
+    /// returns the current fullscreen mode, None if windowed
+    pub fn fullscreen(&self) -> Option<FullscreenMode> {
+        match self.window?.fullscreen()? {
+            winit::window::Fullscreen::Borderless(_) => Some(FullscreenMode::Borderless),
+            winit::window::Fullscreen::Exclusive(_) => Some(FullscreenMode::Exclusive),
+        }
+    }
+    /// switches between borderless fullscreen & windowed
+    pub fn toggle_fullscreen(&self) {
+        if self.fullscreen().is_some() {
+            self.set_fullscreen(None);
+        } else {
+            self.set_fullscreen(Some(FullscreenMode::Borderless));
+        }
+    }
+    /// changes the title of the window
+    pub fn set_title(&mut self, title: &str) {
+        if let Some(window) = self.window {
+            window.set_title(title);
+        }
+        self.config_chargrid.name = title.to_owned();
+    }
+    /// sets the icon of the window (in the taskbar, mostly) from an image
+    pub fn set_icon_image(&self, image: &DynamicImage) -> Result<(), winit::window::BadIcon> {
+        let Some(window) = self.window else {
+            return Ok(());
+        };
+        let image = image.to_rgba8();
+        let (width, height) = image.dimensions();
+        window.set_window_icon(Some(winit::window::Icon::from_rgba(
+            image.into_raw(),
+            width,
+            height,
+        )?));
+        Ok(())
+    }
+    /// sets the icon of the window to one of our glyphs, drawn like it would be in the grid
+    /// chars that aren't in CP437 become `?`
+    pub fn set_icon_glyph(
+        &self,
+        ch: char,
+        set: u8,
+        fg: Col,
+        bg: Col,
+    ) -> Result<(), winit::window::BadIcon> {
+        let glyph = codepage_437::CP437_WINGDINGS.encode(ch).unwrap_or(b'?');
+        let glyph = self.glyph_image(glyph, set, fg, bg);
+        // glyphs are tiny, so we make them big enough for the OS not to blur them too much
+        let scale = (32 / glyph.width().max(1)).max(1);
+        let glyph = image::imageops::resize(
+            &glyph,
+            glyph.width() * scale,
+            glyph.height() * scale,
+            image::imageops::FilterType::Nearest,
+        );
+        self.set_icon_image(&DynamicImage::ImageRgba8(glyph))
+    }
+    /// renders one cell to an image, the same way the shader does (the glyph is `fg` over `bg`, with the font's alpha)
+    /// like in the grid, sets past the last font use the last font
+    pub fn glyph_image(&self, glyph: u8, set: u8, fg: Col, bg: Col) -> image::RgbaImage {
+        let font = &self.images[(set as usize).min(self.images.len() - 1)];
+        let (x0, y0) = (
+            (glyph as u32 % 16) * self.char_width,
+            (glyph as u32 / 16) * self.char_height,
+        );
+        let fg = [fg.0, fg.1, fg.2, fg.3];
+        let bg = [bg.0, bg.1, bg.2, bg.3];
+        image::RgbaImage::from_fn(self.char_width, self.char_height, |x, y| {
+            let texel = font.get_pixel(x0 + x, y0 + y).0;
+            let alpha = texel[3] as f32 / 255.;
+            Rgba(std::array::from_fn(|i| {
+                let fg = fg[i] as f32 * texel[i] as f32 / 255.;
+                let bg = bg[i] as f32;
+                (bg + (fg - bg) * alpha).round() as u8
+            }))
+        })
+    }
+    /// shows or hides the OS cursor while it's over the window
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(window) = self.window {
+            window.set_cursor_visible(visible);
+        }
+    }
+    /// keeps the cursor inside the window (or lets it go)
+    /// some platforms can't confine the cursor, it's locked in place instead there (macOS), and some can't do either (the error says so)
+    pub fn confine_cursor(&self, confine: bool) -> Result<(), winit::error::ExternalError> {
+        let Some(window) = self.window else {
+            return Ok(());
+        };
+        if !confine {
+            return window.set_cursor_grab(winit::window::CursorGrabMode::None);
+        }
+        window
+            .set_cursor_grab(winit::window::CursorGrabMode::Confined)
+            .or_else(|_| window.set_cursor_grab(winit::window::CursorGrabMode::Locked))
+    }
+    /// returns the size (in pixels) of the monitor the window is on, if we know it
+    pub fn monitor_size(&self) -> Option<PhysicalSize<u32>> {
+        self.window?.current_monitor().map(|monitor| monitor.size())
+    }
     // copy pasted from https://sotrh.github.io/learn-wgpu/beginner/tutorial2-surface/#state-new
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        #[cfg(target_arch = "wasm32")]
-        {
-            // resizing on wasm32 crashes; I should investigate that
-            return;
-        }
-        // current resizing only stretches; it would be nice to keep the proportions constant
+        // a minimized window has no size, but the mouse can't be over it either
         if new_size.width > 0 && new_size.height > 0 {
-            self.surface_conf.width = new_size.width;
-            self.surface_conf.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_conf);
+            self.surface_size = new_size;
+        }
+        #[cfg(feature = "gpu")]
+        self.resize_gpu(new_size);
+    }
+    // this function renders everything to the screen, with whichever renderer we have
+    fn draw(&mut self) -> Result<(), RenderError> {
+        // every backend draws, so this is where the grid gets recorded
+        if let Some(mut recorder) = self.grid_recorder.take() {
+            recorder.capture(self);
+            self.grid_recorder = Some(recorder);
+        }
+        #[cfg(feature = "gpu")]
+        if self.gpu.is_some() {
+            return self.draw_gpu();
+        }
+        self.dirty = false;
+        #[cfg(feature = "software")]
+        if self.presenter.is_some() {
+            let image = raster::rasterize(self);
+            if let Some(presenter) = &mut self.presenter {
+                return presenter
+                    .present(&image, self.surface_size)
+                    .map_err(|e| RenderError::Software(e.to_string()));
+            }
+        }
+        // a real window without a renderer lost its GPU & couldn't get it back, so we try again
+        // (once per GPU_RETRY_INTERVAL, since asking for a GPU blocks the frame)
+        #[cfg(feature = "gpu")]
+        if self.window.is_some() {
+            let now = now_ms();
+            if now - self.last_gpu_retry < GPU_RETRY_INTERVAL {
+                return Err(RenderError::NoRenderer);
+            }
+            self.last_gpu_retry = now;
+            self.recreate_gpu();
+            if self.gpu.is_none() {
+                return Err(RenderError::NoRenderer);
+            }
+            return self.draw_gpu();
         }
+        // headless windows have nothing to draw on
+        Ok(())
+    }
+    /// renders the current frame into an image (it's as big as the window was when it was created, before it got stretched)
+    /// it reads the frame back from the GPU if there is one, and uses the software renderer (see the `raster` module) otherwise
+    pub fn screenshot(&mut self) -> image::RgbaImage {
+        #[cfg(feature = "gpu")]
+        if let Some(image) = self.screenshot_gpu() {
+            return image;
+        }
+        raster::rasterize(self)
     }
     /// draws a rectangle of values on the grid
     pub fn draw_rect(
@@ -1421,9 +1096,16 @@ impl<'a> Window<'a> {
     }
 }
 /// The color type used by this crate
+/// (it's a tuple, so serde already knows it)
 pub type Col = (u8, u8, u8, u8);
 
 /// The Config type of this crates which describes how everything functions
+/// with the `serde` feature, it can be read from a file: the missing fields take their default value, and the fonts are never read nor written
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Config {
     /// the size (in characters) of the app
     pub size: (u32, u32),
@@ -1433,9 +1115,10 @@ pub struct Config {
     /// the name of the window
     pub name: String,
     /// if dpi = true, then the size of the window will account for dpi factors
-    /// this currently does nothing :3
+    /// the window is created `scale_factor` times bigger (what we render is stretched to fill it), and it follows the scale factor when moved to another monitor
     pub dpi: bool,
     /// the fonts to use
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub font: Vec<Font>,
     /// the background color
     pub background_color: (u8, u8, u8, u8),
@@ -1447,6 +1130,61 @@ pub struct Config {
     pub max_instances: u32, //.
     /// if we should look for srgb color space
     pub srgb: bool,
+    /// if set, every input is recorded and written to this file when the app exits (see the `replay` module)
+    pub record_input: Option<String>,
+    /// if set, the inputs of this recording are played back (real inputs are ignored until it is finished)
+    pub replay_input: Option<String>,
+    /// if set, what the grid shows is recorded and written to this file when the app exits (see the `grid_recording` module)
+    /// Default: None
+    pub record_grid: Option<String>,
+    /// if we should let the IME (input method editor, for typing chinese, japanese...) compose text
+    /// it makes typing go through `InputEvent::ImeCommit` instead of key presses, so only turn it on if you read text
+    /// Default: false
+    pub ime: bool,
+    /// how long (in ms) a key must be held before it repeats for game actions (see `NiceKeyboard::keys_repeated`)
+    /// Default: 300
+    pub key_repeat_delay: f64,
+    /// the time (in ms) between two repeats of a held key for game actions
+    /// Default: 50
+    pub key_repeat_interval: f64,
+    /// if set, the window starts fullscreen (see `Window::set_fullscreen`)
+    /// Default: None
+    pub fullscreen: Option<FullscreenMode>,
+    /// if set, pressing this saves a screenshot (see `Window::screenshot`) in `screenshot_folder`, for instance `"F12".parse().ok()`
+    /// Default: None
+    pub screenshot_key: Option<keys::Chord>,
+    /// where the screenshots of `screenshot_key` go, they are named after the time they were taken
+    /// Default: "screenshots"
+    pub screenshot_folder: String,
+    /// if set, pressing this starts recording a clip, and pressing it again saves it as a GIF in `screenshot_folder` (see `Window::start_clip`)
+    /// Default: None
+    pub clip_key: Option<keys::Chord>,
+}
+/// How to be fullscreen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FullscreenMode {
+    /// a borderless window that covers the monitor, switching to it is fast & other windows can go on top
+    Borderless,
+    /// takes over the monitor, with its best video mode (biggest, then fastest)
+    Exclusive,
+}
+// converts our fullscreen mode to winit's, on the monitor the window is on
+// None if there is no video mode to use (for exclusive fullscreen)
+fn winit_fullscreen(window: &WinitWindow, mode: FullscreenMode) -> Option<winit::window::Fullscreen> {
+    match mode {
+        FullscreenMode::Borderless => Some(winit::window::Fullscreen::Borderless(None)),
+        FullscreenMode::Exclusive => window
+            .current_monitor()?
+            .video_modes()
+            .max_by_key(|mode| {
+                (
+                    mode.size().width * mode.size().height,
+                    mode.refresh_rate_millihertz(),
+                )
+            })
+            .map(winit::window::Fullscreen::Exclusive),
+    }
 }
 // this is the implementation of the game loop
 impl<'a, T> ApplicationHandler for EventLoopWrapper<T/* <- that T is the game type, provided by the library's user*/>
@@ -1454,7 +1192,21 @@ where
     T: Yarl2Game,
 {
     fn resumed(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
-        // I could handle this later
+        self.game.resumed(&mut self.window);
+    }
+    fn suspended(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.game.suspended(&mut self.window);
+    }
+    fn exiting(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        // this is the last chance to save the recording, whichever way we are exiting
+        if let Some((recording, path)) = &self.recorder {
+            if let Err(_e) = recording.save(path) {
+                // printing breaks wasm32 (and the file system isn't there anyway)
+                #[cfg(not(target_arch = "wasm32"))]
+                eprintln!("couldn't save the input recording to {}: {}", path, _e);
+            }
+        }
+        self.window.save_grid_recording();
     }
     // this is the main function that handles the event loop
 
@@ -1470,9 +1222,44 @@ where
         match event {
             winit::event::WindowEvent::Resized(new_size) => {
                 self.window.resize(new_size);
+                // most platforms don't tell us about minimizing, but a window with no size is as minimized as it gets
+                let minimized = new_size.width == 0 || new_size.height == 0;
+                self.set_minimized(
+                    self.window
+                        .window
+                        .and_then(|window| window.is_minimized())
+                        .unwrap_or(minimized),
+                );
+            }
+            winit::event::WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                mut inner_size_writer,
+            } => {
+                let new_size = self.window.rescale(scale_factor);
+                // with dpi, the window keeps its size for the user (so it gets more pixels on a HiDPI monitor), a `Resized` follows
+                if self.window.config_chargrid.dpi {
+                    let _ = inner_size_writer.request_inner_size(new_size);
+                }
+            }
+            winit::event::WindowEvent::Occluded(occluded) => {
+                // nobody can see the window, so it's the same as being minimized for us
+                self.set_minimized(occluded);
+            }
+            winit::event::WindowEvent::Focused(focused) => {
+                if !focused {
+                    // we won't hear about the keys that are released while we aren't focused, so we release them now
+                    for input in self.keyboard.releases() {
+                        self.input(input);
+                    }
+                }
+                self.game.focus_changed(focused, &mut self.window);
             }
             // this event fires when the user requests the window to close, I think
             winit::event::WindowEvent::CloseRequested => {
+                // the game may want to ask first
+                if !self.game.close_requested(&mut self.window) {
+                    return;
+                }
                 // we first call the close function,
                 self.game.close();
                 // then exit the loop
@@ -1483,32 +1270,83 @@ where
                 state,
                 button,
             } => {
-                if button == MouseButton::Left {
-                    self.keyboard.mouse_pressed = state == ElementState::Pressed;
+                if state == ElementState::Pressed {
+                    self.input(InputEvent::MousePressed(button));
+                } else {
+                    self.input(InputEvent::MouseReleased(button));
                 }
             }
-            
+            winit::event::WindowEvent::MouseWheel {
+                device_id: _,
+                delta,
+                phase: _,
+            } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => {
+                    self.input(InputEvent::WheelLines(x, y));
+                }
+                MouseScrollDelta::PixelDelta(pixels) => {
+                    self.input(InputEvent::WheelPixels(pixels.x, pixels.y));
+                }
+            },
+            winit::event::WindowEvent::CursorEntered { device_id: _ } => {
+                self.input(InputEvent::CursorEntered);
+            }
+            winit::event::WindowEvent::CursorLeft { device_id: _ } => {
+                self.input(InputEvent::CursorLeft);
+            }
             winit::event::WindowEvent::CursorMoved {
                 device_id: _,
                 position,
             } => {
-                // note: this currently breaks on re-size and I should fix that
-                // it's also broken on wasm32
-                let x = position.x - self.window.config_chargrid.padding.0 as f64 / 2.;
-                let y = position.y - self.window.config_chargrid.padding.1 as f64 / 2.;
-                let x = x / self.window.config_chargrid.scale.0 as f64;
-                let y = y / self.window.config_chargrid.scale.1 as f64;
-                let x = x / self.window.char_width as f64;
-                let y = y / self.window.char_height as f64;
-                let pos = (x.floor() as i32, y.floor() as i32);
-                self.keyboard.mouse_position = pos;
+                let pos = self.window.pixel_to_cell(position.x, position.y);
+                self.input(InputEvent::MouseMoved(pos.0, pos.1));
             }
             // when we must render the window
             winit::event::WindowEvent::RedrawRequested => {
+                let now = self.frame_time();
+                // the replayed inputs of this frame happen right before pre_draw, like the real ones
+                if let Some(replay) = &mut self.replay {
+                    let events: Vec<InputEvent> = replay
+                        .events_for_frame(self.frame)
+                        .iter()
+                        .map(|f| f.1.clone())
+                        .collect();
+                    if replay.is_finished() {
+                        // back to the real inputs
+                        self.replay = None;
+                    }
+                    for event in events {
+                        self.dispatch(event);
+                    }
+                }
+                self.keyboard.update_repeats(now);
                 // we first call pre_draw, which should be the main update function the user utilizes
                 self.game.pre_draw(&mut self.window, &mut self.keyboard);
-                // we draw
-                let _ = self.window.draw();
+                self.frame += 1;
+                // we draw (the problems the window can't fix by itself go to the game)
+                if let Err(error) = self.window.draw() {
+                    self.game.render_error(error, &mut self.window);
+                }
+                // the screenshot is taken after the draw, so that it shows what the player just saw
+                if self
+                    .window
+                    .config_chargrid
+                    .screenshot_key
+                    .is_some_and(|key| key.is_just_pressed(&self.keyboard))
+                {
+                    self.save_screenshot();
+                }
+                self.window.capture_clip_frame();
+                if self
+                    .window
+                    .config_chargrid
+                    .clip_key
+                    .is_some_and(|key| key.is_just_pressed(&self.keyboard))
+                {
+                    self.toggle_clip();
+                }
+                // the per-frame parts of the input (wheel, double clicks...) have been seen, so we reset them
+                self.keyboard.end_frame();
                 // we call post_draw (it's mainly intended for time measuring)
                 self.game.post_draw();
                 // we test if we should exit
@@ -1519,43 +1357,57 @@ where
                     event_loop.exit();
                     return;
                 }
-                // we request redraw again, so that we have a true loop
-                self.window.window.request_redraw();
+                // we request redraw again, so that we have a true loop (unless nobody can see it, then restoring the window restarts it)
+                if let (Some(window), false) = (self.window.window, self.minimized) {
+                    window.request_redraw();
+                }
             }
-            // handles keyboard input (terribly, I should improve that system)
+            // handles keyboard input
             winit::event::WindowEvent::KeyboardInput {
                 device_id: _,
                 event,
                 is_synthetic: _,
             } => {
-                if !event.repeat {
-                    if let Some(m) = event.text {
-                        let data = m.chars().next().unwrap();
-                        if event.state.is_pressed() {
-                            self.game.text_input(data, &mut self.window); 
-                            self.keyboard.letters.insert(data);
-                        } else {
-                            self.keyboard.letters.remove(&data);
-                        }
-                    }
-                    if event.state == winit::event::ElementState::Pressed {
-                        self.keyboard.keys.insert(event.physical_key); 
-                    } else {
-
-                        self.keyboard.keys.remove(&event.physical_key);
+                let input = if event.state.is_pressed() {
+                    InputEvent::KeyPressed {
+                        key: event.physical_key,
+                        logical: event.logical_key,
+                        text: event.text.map(|text| text.to_string()),
+                        repeat: event.repeat,
                     }
                 } else {
-                    if let Some(m) = event.text {
-                        let data = m.chars().next().unwrap();
-                        if event.state.is_pressed() {
-                            self.game.text_input(data, &mut self.window); 
-                        } else {
-                         
-                        }
-                     
+                    InputEvent::KeyReleased {
+                        key: event.physical_key,
+                        logical: event.logical_key,
                     }
-                }
+                };
+                self.input(input);
+            }
+            winit::event::WindowEvent::ModifiersChanged(modifiers) => {
+                let state = modifiers.state();
+                self.input(InputEvent::ModifiersChanged(Modifiers {
+                    shift: state.shift_key(),
+                    ctrl: state.control_key(),
+                    alt: state.alt_key(),
+                    super_key: state.super_key(),
+                }));
             }
+            winit::event::WindowEvent::Ime(ime) => match ime {
+                winit::event::Ime::Preedit(text, cursor) => {
+                    self.input(InputEvent::ImePreedit { text, cursor });
+                }
+                winit::event::Ime::Commit(text) => {
+                    self.input(InputEvent::ImeCommit(text));
+                }
+                // a composition can't outlive the IME
+                winit::event::Ime::Disabled => {
+                    self.input(InputEvent::ImePreedit {
+                        text: String::new(),
+                        cursor: None,
+                    });
+                }
+                winit::event::Ime::Enabled => {}
+            },
             _d_o_n_u_t_ => {
                 // I used to debug print, but I no longer do so, since printing breaks wasm32 with my current setup
             }
@@ -1577,9 +1429,40 @@ pub trait Yarl2Game {
     /// will be called if should_exit returns true
     fn close(&mut self) {}
     #[allow(unused)]
+    /// is called when the user tries to close the window (with the close button, alt+f4...)
+    /// return false to keep the window open, to ask "Save before quitting?" for instance (then use `should_exit` to really quit)
+    fn close_requested(&mut self, window: &mut Window) -> bool {
+        true
+    }
+    #[allow(unused)]
+    /// is called when the window gains or loses the focus
+    /// when it's lost, every held key & button has already been released in the `NiceKeyboard` (otherwise they would be stuck)
+    fn focus_changed(&mut self, focused: bool, window: &mut Window) {}
+    #[allow(unused)]
+    /// is called when the window gets minimized (or hidden by other windows) & restored
+    /// no frames happen while it's minimized, so `pre_draw` isn't called
+    fn minimized(&mut self, minimized: bool, window: &mut Window) {}
+    #[allow(unused)]
+    /// is called when the app is suspended (mostly on mobile & web, when it goes to the background)
+    fn suspended(&mut self, window: &mut Window) {}
+    #[allow(unused)]
+    /// is called when the app comes back from being suspended, and once at startup
+    fn resumed(&mut self, window: &mut Window) {}
+    #[allow(unused)]
     /// is called when a character is pressed (useful if you want to read text input)
     /// will trigger from repetition
     fn text_input(&mut self, character: char, window: &mut Window) {}
+    /// is called with the whole text typed by a key, or committed by the IME (which is often more than one char)
+    /// by default, it calls `text_input` for each char
+    fn text_input_str(&mut self, text: &str, window: &mut Window) {
+        for character in text.chars() {
+            self.text_input(character, window);
+        }
+    }
+    #[allow(unused)]
+    /// is called when rendering fails in a way we can't fix by ourselves (see `RenderError`)
+    /// after a device loss, the window has already rebuilt all of its GPU ressources from its CPU-side buffers when this is called
+    fn render_error(&mut self, error: RenderError, window: &mut Window) {}
     /// is called for all events
     fn event(&mut self, _event: &le_winit::event::WindowEvent, _window: &mut Window) {
     }
@@ -1593,10 +1476,7 @@ impl Yarl2Game for () {
             'I',
             WHITE,
             BLACK,
-            [
-                keyboard.mouse_position.0 as f32,
-                keyboard.mouse_position.1 as f32,
-            ],
+            [keyboard.mouse_position_f.0, keyboard.mouse_position_f.1],
             0,
         )); 
         for x in 0..40 {
@@ -1725,6 +1605,120 @@ struct EventLoopWrapper<T: Yarl2Game> {
     game: T,
     window: Window<'static>,
     keyboard: NiceKeyboard,
+    // the amount of times pre_draw was called, recordings are timed with it
+    frame: u64,
+    // where we write the inputs down, with the file we save them to
+    recorder: Option<(InputRecording, String)>,
+    // the recording we are playing back
+    replay: Option<InputReplay>,
+    // what to add to the real clock (`now_ms`) to get the time of a frame, so that it goes on from the replay's once it is finished
+    clock_offset: f64,
+    // no frames happen while this is true
+    minimized: bool,
+}
+impl<T: Yarl2Game> EventLoopWrapper<T> {
+    // saves a screenshot in the screenshot folder, for the screenshot key
+    fn save_screenshot(&mut self) {
+        // there is no file system on the web
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let image = self.window.screenshot();
+            let folder = std::path::Path::new(&self.window.config_chargrid.screenshot_folder);
+            let path = folder.join(format!("{}.png", timestamp()));
+            if let Err(e) = std::fs::create_dir_all(folder)
+                .map_err(image::ImageError::IoError)
+                .and_then(|_| image.save(&path))
+            {
+                eprintln!("couldn't save the screenshot to {}: {}", path.display(), e);
+            }
+        }
+    }
+    // starts recording a clip, or saves the one being recorded in the screenshot folder, for the clip key
+    fn toggle_clip(&mut self) {
+        let Some(clip) = self.window.stop_clip() else {
+            self.window.start_clip();
+            return;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let folder = std::path::Path::new(&self.window.config_chargrid.screenshot_folder);
+            let path = folder.join(format!("{}.gif", timestamp()));
+            if let Err(e) = std::fs::create_dir_all(folder)
+                .map_err(image::ImageError::IoError)
+                .and_then(|_| clip.save_gif(&path))
+            {
+                eprintln!("couldn't save the clip to {}: {}", path.display(), e);
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = clip;
+    }
+    // tells the game when the window gets minimized or restored
+    fn set_minimized(&mut self, minimized: bool) {
+        if minimized == self.minimized {
+            return;
+        }
+        self.minimized = minimized;
+        self.game.minimized(minimized, &mut self.window);
+        // we stopped asking for frames when we got minimized, so we have to start again
+        if !minimized {
+            if let Some(window) = self.window.window {
+                window.request_redraw();
+            }
+        }
+    }
+    // the time (in ms) of the frame that starts: the recorded one while replaying, the real one otherwise (it's recorded too)
+    fn frame_time(&mut self) -> f64 {
+        let real = now_ms();
+        let recorded = self
+            .replay
+            .as_ref()
+            .and_then(|replay| replay.frame_time(self.frame));
+        let time = match recorded {
+            Some(time) => {
+                self.clock_offset = time - real;
+                time
+            }
+            // whole milliseconds, so that a replay gets the exact same times
+            None => (real + self.clock_offset).round(),
+        };
+        if let Some((recording, _)) = &mut self.recorder {
+            recording.push_frame_time(self.frame, time);
+        }
+        time
+    }
+    // every input from the window goes through here, after being turned into an `InputEvent`
+    fn input(&mut self, input: InputEvent) {
+        // while replaying, the real inputs would make the replay diverge
+        if self.replay.is_some() {
+            return;
+        }
+        self.dispatch(input);
+    }
+    // sends an input (real or replayed) to the game & the keyboard
+    fn dispatch(&mut self, input: InputEvent) {
+        if let Some((recording, _)) = &mut self.recorder {
+            recording.push(self.frame, input.clone());
+        }
+        feed_input(&mut self.game, &mut self.window, &mut self.keyboard, input);
+    }
+}
+// sends an input to the game & the keyboard, every way of running a game goes through here
+pub(crate) fn feed_input<T: Yarl2Game>(
+    game: &mut T,
+    window: &mut Window<'static>,
+    keyboard: &mut NiceKeyboard,
+    input: InputEvent,
+) {
+    // text goes to the game directly (repeats included)
+    match &input {
+        InputEvent::KeyPressed {
+            text: Some(text), ..
+        }
+        | InputEvent::ImeCommit(text) => game.text_input_str(text, window),
+        _ => {}
+    }
+    keyboard.handle(input);
 }
 /// Provides input access to the user without the event() function
 pub struct NiceKeyboard {
@@ -1734,11 +1728,422 @@ pub struct NiceKeyboard {
     pub letters: HashSet<char>,
     /// The mouse's position (.0 = x .1 = y like in the rest of this lib)
     pub mouse_position: (i32, i32),
-    /// TODO: implement another button than mouse left
+    /// The mouse's position, but with the position inside the cell as the fractional part
+    /// it uses the same units as `InstanceData::position`, so you can put an instance right under the cursor
+    pub mouse_position_f: (f32, f32),
+    /// true while the left button is held, the same as `mouse_buttons.contains(&MouseButton::Left)`
     pub mouse_pressed: bool,
+    /// The mouse buttons that are held (left, right, middle, back, forward & the others)
+    pub mouse_buttons: HashSet<WinitMouseButton>,
+    /// How much the wheel scrolled since the last frame, in lines (.0 = x .1 = y)
+    /// most mice report lines, touchpads usually report pixels instead
+    pub wheel_lines: (f32, f32),
+    /// How much the wheel scrolled since the last frame, in pixels
+    pub wheel_pixels: (f64, f64),
+    /// The current drag, if a button is held (or was released this frame)
+    pub drag: Option<Drag>,
+    /// true for the frame in which the left button was pressed twice on the same cell in a short time
+    pub double_clicked: bool,
+    /// false when the cursor is outside of the window
+    pub mouse_inside: bool,
+    /// The keys that were pressed since the last frame (even if they were released since)
+    pub keys_just_pressed: HashSet<WinitKey>,
+    /// The keys that were released since the last frame
+    pub keys_just_released: HashSet<WinitKey>,
+    /// The letters that were typed since the last frame (without repeats)
+    pub letters_just_pressed: HashSet<char>,
+    /// The letters whose key was released since the last frame
+    pub letters_just_released: HashSet<char>,
+    /// The mouse buttons that were pressed since the last frame
+    pub mouse_just_pressed: HashSet<WinitMouseButton>,
+    /// The mouse buttons that were released since the last frame
+    pub mouse_just_released: HashSet<WinitMouseButton>,
+    /// The modifiers (shift, ctrl, alt, super) that are held
+    pub modifiers: Modifiers,
+    /// The logical keys that are held, they depend on the keyboard layout & modifiers (Shift+a gives `Character("A")`)
+    pub logical_keys: HashSet<WinitLogicalKey>,
+    /// The logical keys that were pressed since the last frame
+    pub logical_just_pressed: HashSet<WinitLogicalKey>,
+    /// Every input since the last frame, in the order it happened
+    /// unlike the sets, this keeps every keystroke even if you type faster than the framerate
+    pub events: Vec<InputEvent>,
+    /// The keys that were pressed since the last frame, or that are held and repeated this frame
+    /// this is for game actions (moving while an arrow is held...), it's timed with `repeat_delay` & `repeat_interval` instead of the OS's text repeat
+    pub keys_repeated: HashSet<WinitKey>,
+    /// How long (in ms) a key must be held before it repeats, set from `Config::key_repeat_delay`
+    pub repeat_delay: f64,
+    /// The time (in ms) between two repeats, set from `Config::key_repeat_interval`
+    /// if it's 0, held keys repeat every frame once the delay is over
+    pub repeat_interval: f64,
+    /// The text the IME is composing (empty if there is no composition), you should draw it where the text will go
+    pub ime_preedit: String,
+    /// The selected part of `ime_preedit` (in bytes), None if the cursor should be hidden
+    pub ime_cursor: Option<(usize, usize)>,
+    // time (in ms) & cell of the last left click, used to detect double clicks
+    last_click: Option<(f64, (i32, i32))>,
+    // the letter each held key typed, so that we know which letter to release with the key
+    letter_keys: HashMap<WinitKey, char>,
+    // same thing, but for logical keys (the modifiers may have changed between the press & the release)
+    logical_for_key: HashMap<WinitKey, WinitLogicalKey>,
+    // when (in ms) each held key will repeat next
+    repeat_timers: HashMap<WinitKey, f64>,
+    // the time (in ms) of the current frame, given to `update_repeats`
+    // repeats & double clicks are timed with it instead of the real clock, so that replays & tests only depend on the frames
+    now: f64,
+}
+/// The state of the modifier keys
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// the windows/command key
+    pub super_key: bool,
+}
+/// An input, as seen by `NiceKeyboard`
+/// The window turns winit's events into these, but you can also make them yourself to simulate input
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    /// A key was pressed, `repeat` is true if it comes from the key being held down
+    KeyPressed {
+        key: WinitKey,
+        /// the key, according to the keyboard layout
+        logical: WinitLogicalKey,
+        /// the text this key typed, if any
+        text: Option<String>,
+        repeat: bool,
+    },
+    /// A key was released
+    KeyReleased {
+        key: WinitKey,
+        logical: WinitLogicalKey,
+    },
+    /// The held modifiers changed
+    ModifiersChanged(Modifiers),
+    /// A mouse button was pressed
+    MousePressed(WinitMouseButton),
+    /// A mouse button was released
+    MouseReleased(WinitMouseButton),
+    /// The cursor moved to this position, in cells (see `NiceKeyboard::mouse_position_f`)
+    MouseMoved(f32, f32),
+    /// The wheel scrolled, in lines
+    WheelLines(f32, f32),
+    /// The wheel scrolled, in pixels
+    WheelPixels(f64, f64),
+    /// The cursor entered the window
+    CursorEntered,
+    /// The cursor left the window
+    CursorLeft,
+    /// The IME is composing text that isn't typed yet (empty when the composition is over)
+    /// `cursor` is the selected part of the text, in bytes, like winit gives it
+    ImePreedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+    /// The IME typed this text
+    ImeCommit(String),
+}
+/// Describes a mouse drag, in cell coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drag {
+    /// the button that started the drag
+    pub button: WinitMouseButton,
+    /// the cell where the button was pressed
+    pub start: (i32, i32),
+    /// the cell the cursor is currently over
+    pub current: (i32, i32),
+    /// the cell where the button was released, it's only `Some` for the frame in which the drag ended
+    pub end: Option<(i32, i32)>,
+}
+impl Drag {
+    /// returns true if the cursor left the starting cell (otherwise it's more of a click than a drag)
+    pub fn moved(&self) -> bool {
+        self.start != self.current
+    }
+}
+/// the maximum delay (in ms) between two clicks of a double click
+const DOUBLE_CLICK_DELAY: f64 = 400.;
+// the repeat timings of game actions (in ms), when nobody says otherwise
+const DEFAULT_REPEAT_DELAY: f64 = 300.;
+const DEFAULT_REPEAT_INTERVAL: f64 = 50.;
+impl NiceKeyboard {
+    /// creates an empty keyboard (nothing pressed, the mouse at 0, 0)
+    pub fn new() -> Self {
+        Self {
+            keys: HashSet::new(),
+            letters: HashSet::new(),
+            mouse_position: (0, 0),
+            mouse_position_f: (0., 0.),
+            mouse_pressed: false,
+            mouse_buttons: HashSet::new(),
+            wheel_lines: (0., 0.),
+            wheel_pixels: (0., 0.),
+            drag: None,
+            double_clicked: false,
+            mouse_inside: false,
+            keys_just_pressed: HashSet::new(),
+            keys_just_released: HashSet::new(),
+            letters_just_pressed: HashSet::new(),
+            letters_just_released: HashSet::new(),
+            mouse_just_pressed: HashSet::new(),
+            mouse_just_released: HashSet::new(),
+            modifiers: Modifiers::default(),
+            logical_keys: HashSet::new(),
+            logical_just_pressed: HashSet::new(),
+            events: Vec::new(),
+            keys_repeated: HashSet::new(),
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_interval: DEFAULT_REPEAT_INTERVAL,
+            ime_preedit: String::new(),
+            ime_cursor: None,
+            last_click: None,
+            letter_keys: HashMap::new(),
+            logical_for_key: HashMap::new(),
+            repeat_timers: HashMap::new(),
+            now: 0.,
+        }
+    }
+    // a keyboard that repeats keys like the config says
+    fn for_config(config: &Config) -> Self {
+        Self {
+            repeat_delay: config.key_repeat_delay,
+            repeat_interval: config.key_repeat_interval,
+            ..Self::new()
+        }
+    }
+    /// returns true if shift is held
+    pub fn shift(&self) -> bool {
+        self.modifiers.shift
+    }
+    /// returns true if ctrl is held
+    pub fn ctrl(&self) -> bool {
+        self.modifiers.ctrl
+    }
+    /// returns true if alt is held
+    pub fn alt(&self) -> bool {
+        self.modifiers.alt
+    }
+    /// returns true if super (the windows/command key) is held
+    pub fn super_key(&self) -> bool {
+        self.modifiers.super_key
+    }
+    /// returns true if the combo (like `"Ctrl+S"` or `"Shift+ArrowUp"`) is held, with exactly these modifiers
+    /// see `keys::Chord` for the format; invalid combos are never pressed
+    pub fn is_combo_pressed(&self, combo: &str) -> bool {
+        combo
+            .parse::<keys::Chord>()
+            .is_ok_and(|chord| chord.is_pressed(self))
+    }
+    /// same as `is_combo_pressed`, but only true if the key was pressed since the last frame
+    pub fn is_combo_just_pressed(&self, combo: &str) -> bool {
+        combo
+            .parse::<keys::Chord>()
+            .is_ok_and(|chord| chord.is_just_pressed(self))
+    }
+    /// returns true if the key was pressed since the last frame
+    pub fn just_pressed(&self, key: WinitKey) -> bool {
+        self.keys_just_pressed.contains(&key)
+    }
+    /// returns true if the key was released since the last frame
+    pub fn just_released(&self, key: WinitKey) -> bool {
+        self.keys_just_released.contains(&key)
+    }
+    /// feeds an input to the keyboard, as if it came from the window
+    /// the window does this by itself, you only need it to simulate input
+    pub fn handle(&mut self, event: InputEvent) {
+        match &event {
+            InputEvent::KeyPressed {
+                key,
+                logical,
+                text,
+                repeat,
+            } => {
+                // repeats only matter for text input
+                if !repeat {
+                    self.keys.insert(*key);
+                    self.keys_just_pressed.insert(*key);
+                    self.keys_repeated.insert(*key);
+                    self.repeat_timers.insert(*key, self.now + self.repeat_delay);
+                    self.logical_keys.insert(logical.clone());
+                    self.logical_just_pressed.insert(logical.clone());
+                    self.logical_for_key.insert(*key, logical.clone());
+                    if let Some(data) = text.as_ref().and_then(|text| text.chars().next()) {
+                        self.letters.insert(data);
+                        self.letters_just_pressed.insert(data);
+                        self.letter_keys.insert(*key, data);
+                    }
+                }
+            }
+            InputEvent::KeyReleased { key, logical } => {
+                self.keys.remove(key);
+                self.keys_just_released.insert(*key);
+                self.repeat_timers.remove(key);
+                // we release what the key pressed, which may not be what it would type now
+                let logical = self.logical_for_key.remove(key).unwrap_or_else(|| logical.clone());
+                self.logical_keys.remove(&logical);
+                if let Some(data) = self.letter_keys.remove(key) {
+                    self.letters.remove(&data);
+                    self.letters_just_released.insert(data);
+                }
+            }
+            InputEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            InputEvent::MousePressed(button) => self.mouse_button(*button, true),
+            InputEvent::MouseReleased(button) => self.mouse_button(*button, false),
+            InputEvent::MouseMoved(x, y) => self.cursor_moved((*x, *y)),
+            InputEvent::WheelLines(x, y) => {
+                self.wheel_lines.0 += x;
+                self.wheel_lines.1 += y;
+            }
+            InputEvent::WheelPixels(x, y) => {
+                self.wheel_pixels.0 += x;
+                self.wheel_pixels.1 += y;
+            }
+            InputEvent::CursorEntered => self.mouse_inside = true,
+            InputEvent::CursorLeft => self.mouse_inside = false,
+            InputEvent::ImePreedit { text, cursor } => {
+                self.ime_preedit.clone_from(text);
+                self.ime_cursor = *cursor;
+            }
+            InputEvent::ImeCommit(_) => {
+                self.ime_preedit.clear();
+                self.ime_cursor = None;
+            }
+        }
+        self.events.push(event);
+    }
+    /// returns the selected part of `ime_preedit` in chars instead of bytes, which is what you need to draw it in cells
+    pub fn ime_cursor_chars(&self) -> Option<(usize, usize)> {
+        let (start, end) = self.ime_cursor?;
+        let chars = |bytes: usize| self.ime_preedit.get(..bytes).map(|f| f.chars().count());
+        Some((chars(start)?, chars(end)?))
+    }
+    /// returns true if the key was pressed since the last frame, or is held & repeated this frame (see `keys_repeated`)
+    pub fn is_repeated(&self, key: WinitKey) -> bool {
+        self.keys_repeated.contains(&key)
+    }
+    // the inputs that release everything that is held (keys, mouse buttons & modifiers)
+    fn releases(&self) -> Vec<InputEvent> {
+        let mut inputs: Vec<InputEvent> = self
+            .keys
+            .iter()
+            .map(|key| InputEvent::KeyReleased {
+                key: *key,
+                logical: self
+                    .logical_for_key
+                    .get(key)
+                    .cloned()
+                    .unwrap_or(WinitLogicalKey::Unidentified(
+                        winit::keyboard::NativeKey::Unidentified,
+                    )),
+            })
+            .collect();
+        inputs.extend(
+            self.mouse_buttons
+                .iter()
+                .map(|button| InputEvent::MouseReleased(*button)),
+        );
+        if self.modifiers != Modifiers::default() {
+            inputs.push(InputEvent::ModifiersChanged(Modifiers::default()));
+        }
+        inputs
+    }
+    // starts a frame at a time (in ms): fires the repeats of the held keys whose time has come, it's called right before pre_draw
+    fn update_repeats(&mut self, now: f64) {
+        self.now = now;
+        for (key, next) in self.repeat_timers.iter_mut() {
+            if *next <= now {
+                self.keys_repeated.insert(*key);
+                // a slow frame only gives one repeat instead of a burst
+                if self.repeat_interval > 0. {
+                    while *next <= now {
+                        *next += self.repeat_interval;
+                    }
+                } else {
+                    *next = now;
+                }
+            }
+        }
+    }
+    /// returns true if that mouse button is held
+    pub fn is_mouse_down(&self, button: WinitMouseButton) -> bool {
+        self.mouse_buttons.contains(&button)
+    }
+    // updates the state when a mouse button changes
+    fn mouse_button(&mut self, button: WinitMouseButton, pressed: bool) {
+        let pos = self.mouse_position;
+        if pressed {
+            self.mouse_buttons.insert(button);
+            self.mouse_just_pressed.insert(button);
+            // only the first button starts a drag
+            if !matches!(self.drag, Some(Drag { end: None, .. })) {
+                self.drag = Some(Drag {
+                    button,
+                    start: pos,
+                    current: pos,
+                    end: None,
+                });
+            }
+            if button == MouseButton::Left {
+                let now = self.now;
+                match self.last_click {
+                    Some((time, cell)) if cell == pos && now - time <= DOUBLE_CLICK_DELAY => {
+                        self.double_clicked = true;
+                        // a third click starts over instead of being another double click
+                        self.last_click = None;
+                    }
+                    _ => self.last_click = Some((now, pos)),
+                }
+            }
+        } else {
+            self.mouse_buttons.remove(&button);
+            self.mouse_just_released.insert(button);
+            if let Some(drag) = &mut self.drag {
+                if drag.button == button && drag.end.is_none() {
+                    drag.current = pos;
+                    drag.end = Some(pos);
+                }
+            }
+        }
+        self.mouse_pressed = self.mouse_buttons.contains(&MouseButton::Left);
+    }
+    // updates the state when the cursor moves, the position is in cells
+    fn cursor_moved(&mut self, position: (f32, f32)) {
+        self.mouse_position_f = position;
+        self.mouse_position = (position.0.floor() as i32, position.1.floor() as i32);
+        if let Some(drag) = &mut self.drag {
+            if drag.end.is_none() {
+                drag.current = self.mouse_position;
+            }
+        }
+    }
+    // resets everything that only lasts one frame (wheel, double clicks, edges & events), it's called after pre_draw
+    fn end_frame(&mut self) {
+        self.wheel_lines = (0., 0.);
+        self.wheel_pixels = (0., 0.);
+        self.double_clicked = false;
+        self.keys_just_pressed.clear();
+        self.keys_just_released.clear();
+        self.letters_just_pressed.clear();
+        self.letters_just_released.clear();
+        self.mouse_just_pressed.clear();
+        self.mouse_just_released.clear();
+        self.logical_just_pressed.clear();
+        self.keys_repeated.clear();
+        self.events.clear();
+        if matches!(self.drag, Some(Drag { end: Some(_), .. })) {
+            self.drag = None;
+        }
+    }
+}
+impl Default for NiceKeyboard {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub type WinitKey = PhysicalKey;
+pub type WinitMouseButton = MouseButton;
+pub type WinitLogicalKey = winit::keyboard::Key;
 // The default config, uses the font that DEFAULT_FONT_LICENSE refers to
 impl Default for Config {
     fn default() -> Self {
@@ -1755,9 +2160,99 @@ impl Default for Config {
             scale: (2 / 2, 2 / 2),
             max_instances: 128,
             srgb: true,
+            record_input: None,
+            replay_input: None,
+            record_grid: None,
+            ime: false,
+            key_repeat_delay: DEFAULT_REPEAT_DELAY,
+            key_repeat_interval: DEFAULT_REPEAT_INTERVAL,
+            fullscreen: None,
+            screenshot_key: None,
+            screenshot_folder: String::from("screenshots"),
+            clip_key: None,
         } 
     }
 }
+/// An error that happened while rendering, given to `Yarl2Game::render_error`
+#[derive(Debug)]
+pub enum RenderError {
+    /// we couldn't get a frame from the surface (`wgpu::SurfaceError::OutOfMemory`, mostly)
+    /// lost & outdated surfaces are reconfigured and timeouts skip the frame, so you will never see these
+    #[cfg(feature = "gpu")]
+    Surface(wgpu::SurfaceError),
+    /// the GPU device was lost (driver reset, GPU unplugged...), the string is wgpu's explanation
+    #[cfg(feature = "gpu")]
+    DeviceLost(String),
+    /// the GPU was lost & couldn't be created again (and there is no software renderer), so nothing is drawn
+    /// the window tries again every second, and you get this every frame until it works
+    #[cfg(feature = "gpu")]
+    NoRenderer,
+    /// the shader of a post effect didn't compile, the string is wgpu's explanation (the effect is skipped from then on)
+    #[cfg(feature = "gpu")]
+    PostEffect(String),
+    /// the software renderer couldn't show its frame in the window, the string is softbuffer's explanation
+    #[cfg(feature = "software")]
+    Software(String),
+}
+/// Returns the current time (UTC) as `2024-12-31_23-59-59`, for naming files
+#[cfg(not(target_arch = "wasm32"))]
+fn timestamp() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |f| f.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // turns days since 1970 into a date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+/// Returns a time in milliseconds, only useful to measure durations
+fn now_ms() -> f64 {
+    // `std::time::Instant` panics on the web, so we ask the browser instead
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|window| window.performance())
+            .map_or(0., |performance| performance.now())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START
+            .get_or_init(std::time::Instant::now)
+            .elapsed()
+            .as_secs_f64()
+            * 1000.
+    }
+}
+/// Runs a future to completion on whichever executor works on the current target
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    // now, since the default wasm32 target does not support `smol`, we must use a different crate to create all the variables, since they are created by our async function
+    #[cfg(target_arch = "wasm32")]
+    {
+        wasm_rs_async_executor::single_threaded::block_on(future)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        smol::block_on(future)
+    }
+}
 /// Runs the game
 pub fn run_game<T>(game: T, config: Config) -> !
 where
@@ -1773,7 +2268,8 @@ pub use winit::keyboard::KeyCode as TheKeyCodeTypeFromWinit;
 /// an instance for instanced rendering of chars, unaligned to the grid (floating characters!)
 /// recommended to be used with the provided constructor
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InstanceData {
     /// represents the top left position of the char
     /// one represents one character on the grid
@@ -1796,42 +2292,12 @@ impl InstanceData {
         }
     }
 }
-/// The layout of the instances, copy-pasted again from the wgpu tutorial
-const INSTANCE_LAYOUT: wgpu::VertexBufferLayout =
-    /* more copy-pasting :3 */ /* moare copy-pasting :> */
-    wgpu::VertexBufferLayout {
-        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress, // 1.
-        step_mode: wgpu::VertexStepMode::Instance,                          // 2.
-        attributes: &[
-            // 3.//Vertex
-            wgpu::VertexAttribute {
-                offset: 0,                             // 4.
-                shader_location: 2,                    // 5.
-                format: wgpu::VertexFormat::Float32x2, // 6.
-            }, //0
-            wgpu::VertexAttribute {
-                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress, //3//2
-                shader_location: 3,
-                format: wgpu::VertexFormat::Uint8x2, //3
-            }, //1
-            wgpu::VertexAttribute {
-                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress
-                    + std::mem::size_of::<[u8; 2]>() as wgpu::BufferAddress, //3//2
-                shader_location: 4,
-                format: wgpu::VertexFormat::Unorm8x4, //3
-            }, //2
-            wgpu::VertexAttribute {
-                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress
-                    + std::mem::size_of::<[u8; 2 + 4]>() as wgpu::BufferAddress, //3//2
-                shader_location: 2 + 1 + 2,           // aka 5
-                format: wgpu::VertexFormat::Unorm8x4, //3
-            },
-        ],
-    };
 unsafe impl bytemuck::Pod for InstanceData {}
 unsafe impl bytemuck::Zeroable for InstanceData {}
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a snapshot taken from screen memory, which can then be drawn
+/// it can be saved & loaded with `write_to`/`read_from` (see the `snapshot` module)
 pub struct Snapshot {
     pub begin: (u32, u32),
     pub size: (u32, u32),
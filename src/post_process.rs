@@ -0,0 +1,152 @@
+//! Post-processing: with effects, the frame is drawn into a texture first, then each effect draws the previous result into the next one,
+//! and the last one draws into the window (see `Window::add_post_effect`)
+//!
+//! ```ignore
+//! window.add_post_effect(PostEffect::crt());
+//! // or your own, the shader defines `effect`, which returns the color at a point of the screen
+//! window.add_post_effect(PostEffect::custom(
+//!     "fn effect(uv: vec2<f32>) -> vec4<f32> {
+//!         let color = source(uv);
+//!         return vec4<f32>(color.rgb * post.params[0].rgb, color.a);
+//!     }",
+//! ));
+//! ```
+//! What the effects' WGSL can use:
+//! - `source(uv)`: the color of the previous result at a point, from (0, 0) at the top left to (1, 1) at the bottom right (filtered linearly)
+//! - `post.resolution`: the size of the textures in pixels (the window's size when it was created, like screenshots)
+//! - `post.time`: the time since the window was created, in seconds
+//! - `post.params[0]` & `post.params[1]`: two vec4s of your own, see `PostEffect::params`
+//!
+//! Notes:
+//! - effects only run on the GPU, the software renderer & the terminal ignore them
+//! - an effect that doesn't compile is reported once with `RenderError::PostEffect`, then skipped
+#[cfg(feature = "gpu")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// every shader gets its own id, so that the GPU only compiles it once
+#[cfg(feature = "gpu")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// what comes before the effects' WGSL: the bindings, the helpers & the shader entry points
+#[cfg(feature = "gpu")]
+const PRELUDE: &str = "
+struct PostUniforms {
+    resolution: vec2<f32>,
+    time: f32,
+    params: array<vec4<f32>, 2>,
+}
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniforms;
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+// a single triangle that covers the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(out.uv.x * 2. - 1., 1. - out.uv.y * 2., 0., 1.);
+    return out;
+}
+// the explicit level lets effects sample anywhere, even after branching
+fn source(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(t_source, s_source, uv, 0.);
+}
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return effect(in.uv);
+}
+";
+// params[0]: curvature, scanlines, vignette, bloom; params[1].x: flicker
+const CRT: &str = "
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    // the screen bulges out, more in the corners
+    let centered = uv * 2. - 1.;
+    let bent = centered + centered * centered.yx * centered.yx * post.params[0].x;
+    let screen = bent * 0.5 + 0.5;
+    if any(screen < vec2<f32>(0.)) || any(screen > vec2<f32>(1.)) {
+        return vec4<f32>(0., 0., 0., 1.);
+    }
+    var color = source(screen).rgb;
+    // the bright parts bleed around them
+    let pixel = 1. / post.resolution;
+    var glow = vec3<f32>(0.);
+    for (var i = -2; i <= 2; i++) {
+        for (var j = -2; j <= 2; j++) {
+            glow += max(source(screen + vec2<f32>(f32(i), f32(j)) * pixel * 1.5).rgb - 0.5, vec3<f32>(0.));
+        }
+    }
+    color += glow / 25. * post.params[0].w * 2.;
+    // a dark line every other row of pixels
+    let line = 0.5 + 0.5 * sin(screen.y * post.resolution.y * 3.14159265);
+    color *= mix(1., line, post.params[0].y);
+    // darker corners
+    let edges = screen.x * screen.y * (1. - screen.x) * (1. - screen.y) * 16.;
+    color *= mix(1., clamp(pow(edges, 0.3), 0., 1.), post.params[0].z);
+    color *= 1. - post.params[1].x * (0.5 + 0.5 * sin(post.time * 60.));
+    return vec4<f32>(color, 1.);
+}
+";
+// params[0].x: the size of the blocks, in pixels
+const PIXELATE: &str = "
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    let block = max(post.params[0].x, 1.);
+    let center = (floor(uv * post.resolution / block) + 0.5) * block / post.resolution;
+    return source(center);
+}
+";
+
+/// A post-processing pass
+/// cloning it is cheap, and the clones share the compiled shader
+#[derive(Clone, Debug)]
+pub struct PostEffect {
+    source: Arc<str>,
+    #[cfg(feature = "gpu")]
+    id: u64,
+    /// two vec4s given to the shader as `post.params`, what they mean is up to it
+    pub params: [[f32; 4]; 2],
+}
+impl PostEffect {
+    /// an effect from WGSL, which must define `fn effect(uv: vec2<f32>) -> vec4<f32>` (see the module's docs for what it can use)
+    pub fn custom(wgsl: &str) -> Self {
+        Self {
+            source: wgsl.into(),
+            #[cfg(feature = "gpu")]
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            params: [[0.; 4]; 2],
+        }
+    }
+    /// an old CRT screen: curvature, scanlines, vignette & bloom, with the usual amounts
+    /// params[0] is (curvature, scanlines, vignette, bloom) & params[1].x is the flicker, from 0 (none) to 1
+    pub fn crt() -> Self {
+        let mut effect = Self::custom(CRT);
+        effect.params = [[0.1, 0.35, 0.5, 0.4], [0.02, 0., 0., 0.]];
+        effect
+    }
+    /// big pixels: every block of `size`×`size` pixels gets the color of its center (params[0].x is the size)
+    pub fn pixelate(size: f32) -> Self {
+        let mut effect = Self::custom(PIXELATE);
+        effect.params[0][0] = size;
+        effect
+    }
+    /// the effect's own WGSL
+    pub fn wgsl(&self) -> &str {
+        &self.source
+    }
+    // identifies the shader for the GPU's pipeline cache
+    #[cfg(feature = "gpu")]
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+    // the whole shader: the prelude, then the effect
+    #[cfg(feature = "gpu")]
+    pub(crate) fn shader(&self) -> String {
+        format!("{}\n{}", PRELUDE, self.source)
+    }
+}
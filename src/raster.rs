@@ -0,0 +1,381 @@
+//! Software rendering: draws the grid, the instances & the image layers into an image, without any GPU
+//! It does the same math as `text_shader.wglsl`, `instance_shader.wglsl` & `image_shader.wglsl` (and the blending of their pipelines),
+//! so its images match what the GPU draws (up to rounding)
+//! With the `software` feature, it also draws in the window when there is no GPU (see `Presenter`)
+use image::{Rgba, RgbaImage};
+
+use crate::{image_layer::ImageLayer, Col, InstanceData, Window};
+
+/// Draws everything the window would draw (background, images, grid & instances) into an image
+/// it's as big as the window was when it was created, before it got stretched
+pub fn rasterize(window: &Window) -> RgbaImage {
+    Scene::capture(window).draw(&fonts(window))
+}
+// the fonts of the window, decoded for `Scene::draw`
+pub(crate) fn fonts(window: &Window) -> Vec<RgbaImage> {
+    window.images.iter().map(|f| f.to_rgba8()).collect()
+}
+// everything that `rasterize` needs from the window (except the fonts, which never change), so that it can be drawn later
+// it's much smaller than the image it gives, which is why clips keep these (see `clip::ClipRecorder`)
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Scene {
+    size: (u32, u32),
+    grid_size: (u32, u32),
+    char_size: (u32, u32),
+    scale: (u32, u32),
+    padding: (u32, u32),
+    srgb: bool,
+    background_color: Col,
+    chars: Vec<u8>,
+    sets: Vec<u8>,
+    fg: Vec<u8>,
+    bg: Vec<u8>,
+    instances: Vec<InstanceData>,
+    background_image: Option<ImageLayer>,
+    overlays: Vec<ImageLayer>,
+}
+impl Scene {
+    // what the window shows right now (the image layers share their images with the window)
+    pub(crate) fn capture(window: &Window) -> Self {
+        let config = &window.config_chargrid;
+        Self {
+            size: (window.size.width, window.size.height),
+            grid_size: config.size,
+            char_size: (window.char_width, window.char_height),
+            scale: config.scale,
+            padding: config.padding,
+            srgb: config.srgb,
+            background_color: window.background_color,
+            chars: window.buffer_chars.clone(),
+            sets: window.set_buffer.clone(),
+            fg: window.buffer_colors_fg.clone(),
+            bg: window.buffer_colors_bg.clone(),
+            instances: window.instances().to_vec(),
+            background_image: window.background_image.clone(),
+            overlays: window.overlays.clone(),
+        }
+    }
+    // draws the scene with the window's fonts (see `fonts`)
+    pub(crate) fn draw(&self, fonts: &[RgbaImage]) -> RgbaImage {
+        let mut canvas = Canvas::new(self, fonts);
+        if let Some(background) = &self.background_image {
+            canvas.draw_image(self, background);
+        }
+        canvas.draw_grid(self);
+        canvas.draw_instances(self);
+        for overlay in &self.overlays {
+            canvas.draw_image(self, overlay);
+        }
+        canvas.finish(self.srgb)
+    }
+}
+// a framebuffer of linear colors, which is what the GPU blends with
+struct Canvas<'a> {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+    fonts: &'a [RgbaImage],
+    // the fonts are sRGB textures, so the GPU turns their colors linear when it reads them
+    to_linear: [f32; 256],
+}
+impl<'a> Canvas<'a> {
+    // a canvas cleared with the background color
+    fn new(scene: &Scene, fonts: &'a [RgbaImage]) -> Self {
+        let background = scene.background_color;
+        let clear = [
+            background.0 as f32 / 255.,
+            background.1 as f32 / 255.,
+            background.2 as f32 / 255.,
+            background.3 as f32 / 255.,
+        ];
+        Self {
+            width: scene.size.0,
+            height: scene.size.1,
+            pixels: vec![clear; (scene.size.0 * scene.size.1) as usize],
+            fonts,
+            to_linear: std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.)),
+        }
+    }
+    // reads a pixel of a glyph, like `textureSample` would (the set is clamped like a texture array layer)
+    fn texel(&self, set: u8, glyph: u8, x: u32, y: u32, cell: (u32, u32)) -> [f32; 4] {
+        let font = &self.fonts[(set as usize).min(self.fonts.len() - 1)];
+        let texel = font.get_pixel(
+            (glyph as u32 % 16) * cell.0 + x,
+            (glyph as u32 / 16) * cell.1 + y,
+        );
+        [
+            self.to_linear[texel[0] as usize],
+            self.to_linear[texel[1] as usize],
+            self.to_linear[texel[2] as usize],
+            texel[3] as f32 / 255.,
+        ]
+    }
+    // blends a color over a pixel like the pipelines do: SrcAlpha/OneMinusSrcAlpha for the color, OVER for the alpha
+    fn blend(&mut self, x: u32, y: u32, color: [f32; 4]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        // the shaders' outputs are clamped since the surface is a normalized format
+        let alpha = color[3].clamp(0., 1.);
+        let pixel = &mut self.pixels[(x + y * self.width) as usize];
+        for (dst, src) in pixel.iter_mut().zip(color).take(3) {
+            *dst = src.clamp(0., 1.) * alpha + *dst * (1. - alpha);
+        }
+        pixel[3] = alpha + pixel[3] * (1. - alpha);
+    }
+    // the text shader: every cell is its glyph, `fg` over `bg`
+    fn draw_grid(&mut self, scene: &Scene) {
+        let cell = scene.char_size;
+        let scale = scene.scale;
+        // the padding is scaled with the rest
+        let start = (scene.padding.0 * scale.0 / 2, scene.padding.1 * scale.1 / 2);
+        for cy in 0..scene.grid_size.1 {
+            for cx in 0..scene.grid_size.0 {
+                let index = (cx + cy * scene.grid_size.0) as usize;
+                let glyph = scene.chars[index];
+                let set = scene.sets[index];
+                let fg = color(&scene.fg[index * 4..index * 4 + 4]);
+                let bg = color(&scene.bg[index * 4..index * 4 + 4]);
+                for gy in 0..cell.1 {
+                    for gx in 0..cell.0 {
+                        let texel = self.texel(set, glyph, gx, gy, cell);
+                        // mix(col_bg, col_char * col_fg, col_char.a), with col.a = col_char.a + col_bg.a
+                        let mut out: [f32; 4] =
+                            std::array::from_fn(|i| bg[i] + (texel[i] * fg[i] - bg[i]) * texel[3]);
+                        out[3] = texel[3] + bg[3];
+                        let x = start.0 + (cx * cell.0 + gx) * scale.0;
+                        let y = start.1 + (cy * cell.1 + gy) * scale.1;
+                        for dy in 0..scale.1 {
+                            for dx in 0..scale.0 {
+                                self.blend(x + dx, y + dy, out);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    // the instance shader: every instance is a glyph-sized quad that can be anywhere
+    fn draw_instances(&mut self, scene: &Scene) {
+        let cell = scene.char_size;
+        let (scale_x, scale_y) = (scene.scale.0 as f32, scene.scale.1 as f32);
+        let (width, height) = (cell.0 as f32 * scale_x, cell.1 as f32 * scale_y);
+        for instance in &scene.instances {
+            // the instance shader puts the quad one cell lower than you would think, minus the padding (see the comment in there)
+            // we do the same thing so that the images match
+            let x0 = (instance.position[0] * cell.0 as f32 + scene.padding.0 as f32 / 2.) * scale_x;
+            let y0 = ((instance.position[1] + 1.) * cell.1 as f32 - scene.padding.1 as f32 / 2.)
+                * scale_y;
+            let fg = color(&[instance.fg.0, instance.fg.1, instance.fg.2, instance.fg.3]);
+            let bg = color(&[instance.bg.0, instance.bg.1, instance.bg.2, instance.bg.3]);
+            let [set, glyph] = instance.set_char;
+            // the pixels whose center is inside of the quad
+            let first_x = (x0 - 0.5).ceil().max(0.) as u32;
+            let first_y = (y0 - 0.5).ceil().max(0.) as u32;
+            let last_x = ((x0 + width - 0.5).ceil().max(0.) as u32).min(self.width);
+            let last_y = ((y0 + height - 0.5).ceil().max(0.) as u32).min(self.height);
+            for y in first_y..last_y {
+                for x in first_x..last_x {
+                    let u = (x as f32 + 0.5 - x0) / width;
+                    let v = (y as f32 + 0.5 - y0) / height;
+                    let texel = self.texel(
+                        set,
+                        glyph,
+                        ((u * cell.0 as f32) as u32).min(cell.0 - 1),
+                        ((v * cell.1 as f32) as u32).min(cell.1 - 1),
+                        cell,
+                    );
+                    // mix(in.bg, col_char * in.fg, col_char.a * in.fg.a)
+                    let amount = texel[3] * fg[3];
+                    let out = std::array::from_fn(|i| bg[i] + (texel[i] * fg[i] - bg[i]) * amount);
+                    self.blend(x, y, out);
+                }
+            }
+        }
+    }
+    // the image shader: a textured quad, sampled like the fonts (nearest pixel, sRGB)
+    fn draw_image(&mut self, scene: &Scene, layer: &ImageLayer) {
+        let image = layer.image();
+        if image.width() == 0 || image.height() == 0 {
+            return;
+        }
+        let (left, top, right, bottom) =
+            layer.pixel_rect(scene.char_size, scene.padding, scene.scale);
+        let alpha = layer.alpha.clamp(0., 1.);
+        // the pixels whose center is inside of the quad
+        let first_x = (left - 0.5).ceil().max(0.) as u32;
+        let first_y = (top - 0.5).ceil().max(0.) as u32;
+        let last_x = ((right - 0.5).ceil().max(0.) as u32).min(self.width);
+        let last_y = ((bottom - 0.5).ceil().max(0.) as u32).min(self.height);
+        for y in first_y..last_y {
+            for x in first_x..last_x {
+                let u = (x as f32 + 0.5 - left) / (right - left);
+                let v = (y as f32 + 0.5 - top) / (bottom - top);
+                let texel = image.get_pixel(
+                    ((u * image.width() as f32) as u32).min(image.width() - 1),
+                    ((v * image.height() as f32) as u32).min(image.height() - 1),
+                );
+                self.blend(
+                    x,
+                    y,
+                    [
+                        self.to_linear[texel[0] as usize],
+                        self.to_linear[texel[1] as usize],
+                        self.to_linear[texel[2] as usize],
+                        texel[3] as f32 / 255. * alpha,
+                    ],
+                );
+            }
+        }
+    }
+    // turns the framebuffer into an image, an sRGB surface encodes the colors when they are written
+    fn finish(self, srgb: bool) -> RgbaImage {
+        // a table is much faster than powf for every pixel, and 4096 steps are plenty for 256 outputs
+        let to_srgb: Vec<u8> = (0..4096)
+            .map(|i| {
+                let value = i as f32 / 4095.;
+                let value = if srgb { linear_to_srgb(value) } else { value };
+                (value * 255.).round() as u8
+            })
+            .collect();
+        let encode = |value: f32| to_srgb[(value.clamp(0., 1.) * 4095.).round() as usize];
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let pixel = self.pixels[(x + y * self.width) as usize];
+            Rgba([
+                encode(pixel[0]),
+                encode(pixel[1]),
+                encode(pixel[2]),
+                (pixel[3].clamp(0., 1.) * 255.).round() as u8,
+            ])
+        })
+    }
+}
+// the fg & bg buffers are plain unorm textures: no conversion, just 0..1
+fn color(bytes: &[u8]) -> [f32; 4] {
+    std::array::from_fn(|i| bytes[i] as f32 / 255.)
+}
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+/// Shows the software renderer's frames in a window, with softbuffer
+#[cfg(feature = "software")]
+pub struct Presenter {
+    surface: softbuffer::Surface<&'static winit::window::Window, &'static winit::window::Window>,
+    // the size the surface was last resized to
+    size: (u32, u32),
+}
+#[cfg(feature = "software")]
+impl Presenter {
+    /// None if softbuffer doesn't work on this platform
+    pub fn new(window: &'static winit::window::Window) -> Option<Self> {
+        let context = softbuffer::Context::new(window).ok()?;
+        let surface = softbuffer::Surface::new(&context, window).ok()?;
+        Some(Self {
+            surface,
+            size: (0, 0),
+        })
+    }
+    /// shows an image in the window, stretched to its size (like the GPU renderer does)
+    pub fn present(
+        &mut self,
+        image: &RgbaImage,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Result<(), softbuffer::SoftBufferError> {
+        let (Some(width), Some(height)) = (
+            std::num::NonZeroU32::new(size.width),
+            std::num::NonZeroU32::new(size.height),
+        ) else {
+            // nothing to draw in
+            return Ok(());
+        };
+        if self.size != (size.width, size.height) {
+            self.surface.resize(width, height)?;
+            self.size = (size.width, size.height);
+        }
+        let mut buffer = self.surface.buffer_mut()?;
+        for y in 0..size.height {
+            let source_y = (y as u64 * image.height() as u64 / size.height as u64) as u32;
+            for x in 0..size.width {
+                let source_x = (x as u64 * image.width() as u64 / size.width as u64) as u32;
+                let pixel = image.get_pixel(source_x, source_y);
+                // softbuffer wants 0RGB
+                buffer[(x + y * size.width) as usize] =
+                    (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | pixel[2] as u32;
+            }
+        }
+        buffer.present()
+    }
+}
+/// Without the `software` feature, there is never a presenter
+#[cfg(not(feature = "software"))]
+pub type Presenter = std::convert::Infallible;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{colors::BLACK, Config};
+
+    // the pixel in the middle of a cell
+    fn middle(window: &Window, x: u32, y: u32) -> (u32, u32) {
+        let config = &window.config_chargrid;
+        let (scale, padding) = (config.scale, config.padding);
+        (
+            padding.0 * scale.0 / 2 + (x * window.char_width + window.char_width / 2) * scale.0,
+            padding.1 * scale.1 / 2 + (y * window.char_height + window.char_height / 2) * scale.1,
+        )
+    }
+
+    #[test]
+    fn cells_are_drawn_where_they_are() {
+        let mut window = Window::headless(Config {
+            srgb: false,
+            ..Config::default()
+        });
+        window.set_bg_at(2, 1, (255, 0, 0, 255));
+        // a full block is all fg
+        window.set_char_at(3, 1, '█');
+        window.set_fg_at(3, 1, (0, 255, 0, 255));
+        window.set_bg_at(3, 1, (0, 0, 255, 255));
+        let image = rasterize(&window);
+        assert_eq!(
+            (image.width(), image.height()),
+            (window.size.width, window.size.height)
+        );
+        let pixel = |x, y| {
+            let (px, py) = middle(&window, x, y);
+            *image.get_pixel(px, py)
+        };
+        assert_eq!(pixel(2, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(pixel(3, 1), Rgba([0, 255, 0, 255]));
+        // the others show the background color
+        let background = window.background_color;
+        assert_eq!(
+            pixel(0, 0),
+            Rgba([background.0, background.1, background.2, background.3])
+        );
+    }
+    #[test]
+    fn sets_past_the_last_font_use_the_last_one() {
+        let mut window = Window::headless(Config {
+            srgb: false,
+            ..Config::default()
+        });
+        window.set_char_at(0, 0, '█');
+        window.set_fg_at(0, 0, (0, 255, 0, 255));
+        window.set_bg_at(0, 0, BLACK);
+        let image = rasterize(&window);
+        // `set_set_at` refuses it, but snapshots & recordings made with more fonts can have it
+        window.set_buffer[0] = 200;
+        assert_eq!(rasterize(&window), image);
+    }
+}
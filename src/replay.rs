@@ -0,0 +1,425 @@
+//! Input recording & deterministic replay
+//! Every `InputEvent` the window sees can be written down with the frame it happened on, then fed back through
+//! `NiceKeyboard` & `Yarl2Game::text_input` on the same frames (see `Config::record_input` & `Config::replay_input`)
+//! Notes:
+//! - `Yarl2Game::event` only ever receives the real winit events, since winit doesn't let us make keyboard events ourselves
+//! - the mouse is recorded in cells, so the window size doesn't matter
+//! - the time of every frame is recorded too, since key repeats & double clicks are timed with it (see `InputRecording::frame_times`)
+//!
+//! The file format is compact & binary: a `YRLI` header, a version byte, the amount of frame times followed by
+//! the times (as varint deltas from the previous one, in ms), then one record per event made of
+//! the frame (as a varint delta from the previous record), a tag byte and the event's data
+//! (files from older versions can always be read, files from newer versions can't)
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use winit::keyboard::{Key, NativeKey, NativeKeyCode, SmolStr};
+
+use crate::{
+    binary::{invalid, write_varint, Input},
+    keys::{KEY_CODES, NAMED_KEYS},
+    InputEvent, Modifiers, WinitKey, WinitLogicalKey, WinitMouseButton,
+};
+
+const MAGIC: &[u8; 4] = b"YRLI";
+// 2 added the IME events & the frame times
+const VERSION: u8 = 2;
+// the marker for keys that aren't in our tables
+const UNKNOWN_KEY: u16 = u16::MAX;
+
+/// A list of inputs with the frame they happened on
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputRecording {
+    /// the inputs, sorted by frame; frame n's inputs happen right before the n-th call to `pre_draw` (starting at 0)
+    pub events: Vec<(u64, InputEvent)>,
+    /// when each frame started, in ms (frame n's time is at index n)
+    /// the keyboard times key repeats & double clicks with it, so replaying it gives the same frames
+    /// it's empty for recordings of version 1, which are replayed with the real clock
+    pub frame_times: Vec<u64>,
+}
+impl InputRecording {
+    /// creates an empty recording
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// adds an input at a frame, which must not be before the last one
+    pub fn push(&mut self, frame: u64, event: InputEvent) {
+        debug_assert!(
+            self.events.last().is_none_or(|f| f.0 <= frame),
+            "inputs must be recorded in order"
+        );
+        self.events.push((frame, event));
+    }
+    // writes down when a frame started, frames must come in order (& only once)
+    pub(crate) fn push_frame_time(&mut self, frame: u64, time: f64) {
+        if frame == self.frame_times.len() as u64 {
+            // the time never goes back, since the times are saved as deltas
+            let last = self.frame_times.last().copied().unwrap_or(0);
+            self.frame_times.push((time.max(0.) as u64).max(last));
+        }
+    }
+    /// returns the frame of the last input (0 if it's empty)
+    pub fn last_frame(&self) -> u64 {
+        self.events.last().map_or(0, |f| f.0)
+    }
+    /// writes the recording in the binary format
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        write_varint(&mut out, self.frame_times.len() as u64);
+        let mut last = 0;
+        for time in &self.frame_times {
+            write_varint(&mut out, time - last);
+            last = *time;
+        }
+        let mut last = 0;
+        for (frame, event) in &self.events {
+            write_varint(&mut out, frame - last);
+            last = *frame;
+            write_event(&mut out, event);
+        }
+        writer.write_all(&out)
+    }
+    /// reads a recording written by `write_to`
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut input = Input {
+            data: &data,
+            pos: 0,
+        };
+        if input.bytes(4)? != MAGIC {
+            return Err(invalid("not an input recording"));
+        }
+        let version = input.u8()?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(invalid("unsupported input recording version"));
+        }
+        let mut recording = Self::new();
+        if version >= 2 {
+            // each time takes at least a byte, so a bad count stops at the end of the file
+            let mut time: u64 = 0;
+            for _ in 0..input.varint()? {
+                time = input
+                    .varint()?
+                    .checked_add(time)
+                    .ok_or_else(|| invalid("frame time too big"))?;
+                recording.frame_times.push(time);
+            }
+        }
+        let mut frame = 0;
+        while !input.is_empty() {
+            frame = input
+                .varint()?
+                .checked_add(frame)
+                .ok_or_else(|| invalid("frame too big"))?;
+            let event = read_event(&mut input)?;
+            recording.push(frame, event);
+        }
+        Ok(recording)
+    }
+    /// saves the recording to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+    /// loads a recording from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::read_from(std::fs::File::open(path)?)
+    }
+}
+/// Plays a recording back, frame by frame
+#[derive(Clone, Debug)]
+pub struct InputReplay {
+    recording: InputRecording,
+    // index of the next input to give
+    next: usize,
+}
+impl InputReplay {
+    /// starts replaying from the first frame
+    pub fn new(recording: InputRecording) -> Self {
+        Self { recording, next: 0 }
+    }
+    /// returns the inputs of a frame; frames must be asked in order, and asking for a frame skips the ones before it
+    pub fn events_for_frame(&mut self, frame: u64) -> &[(u64, InputEvent)] {
+        let events = &self.recording.events;
+        while self.next < events.len() && events[self.next].0 < frame {
+            self.next += 1;
+        }
+        let start = self.next;
+        while self.next < events.len() && events[self.next].0 == frame {
+            self.next += 1;
+        }
+        &events[start..self.next]
+    }
+    /// returns the time (in ms) the frame started at when it was recorded, None if it wasn't recorded
+    pub fn frame_time(&self, frame: u64) -> Option<f64> {
+        let index = usize::try_from(frame).ok()?;
+        self.recording.frame_times.get(index).map(|f| *f as f64)
+    }
+    /// returns true once every input was given
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.events.len()
+    }
+}
+fn write_str(out: &mut Vec<u8>, text: &str) {
+    write_varint(out, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+fn write_physical(out: &mut Vec<u8>, key: &WinitKey) {
+    let index = match key {
+        WinitKey::Code(code) => KEY_CODES
+            .iter()
+            .position(|f| f == code)
+            .map_or(UNKNOWN_KEY, |i| i as u16),
+        WinitKey::Unidentified(_) => UNKNOWN_KEY,
+    };
+    out.extend_from_slice(&index.to_le_bytes());
+}
+fn write_logical(out: &mut Vec<u8>, key: &WinitLogicalKey) {
+    match key {
+        Key::Character(text) => {
+            out.push(0);
+            write_str(out, text);
+        }
+        Key::Named(named) => {
+            out.push(1);
+            let index = NAMED_KEYS
+                .iter()
+                .position(|f| f == named)
+                .map_or(UNKNOWN_KEY, |i| i as u16);
+            out.extend_from_slice(&index.to_le_bytes());
+        }
+        Key::Dead(ch) => {
+            out.push(2);
+            out.extend_from_slice(&ch.map_or(u32::MAX, |ch| ch as u32).to_le_bytes());
+        }
+        Key::Unidentified(_) => out.push(3),
+    }
+}
+fn write_button(out: &mut Vec<u8>, button: &WinitMouseButton) {
+    match button {
+        WinitMouseButton::Left => out.push(0),
+        WinitMouseButton::Right => out.push(1),
+        WinitMouseButton::Middle => out.push(2),
+        WinitMouseButton::Back => out.push(3),
+        WinitMouseButton::Forward => out.push(4),
+        WinitMouseButton::Other(n) => {
+            out.push(5);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+fn write_event(out: &mut Vec<u8>, event: &InputEvent) {
+    match event {
+        InputEvent::KeyPressed {
+            key,
+            logical,
+            text,
+            repeat,
+        } => {
+            out.push(0);
+            write_physical(out, key);
+            write_logical(out, logical);
+            out.push(*repeat as u8);
+            match text {
+                Some(text) => {
+                    out.push(1);
+                    write_str(out, text);
+                }
+                None => out.push(0),
+            }
+        }
+        InputEvent::KeyReleased { key, logical } => {
+            out.push(1);
+            write_physical(out, key);
+            write_logical(out, logical);
+        }
+        InputEvent::ModifiersChanged(modifiers) => {
+            out.push(2);
+            out.push(
+                modifiers.shift as u8
+                    | (modifiers.ctrl as u8) << 1
+                    | (modifiers.alt as u8) << 2
+                    | (modifiers.super_key as u8) << 3,
+            );
+        }
+        InputEvent::MousePressed(button) => {
+            out.push(3);
+            write_button(out, button);
+        }
+        InputEvent::MouseReleased(button) => {
+            out.push(4);
+            write_button(out, button);
+        }
+        InputEvent::MouseMoved(x, y) => {
+            out.push(5);
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+        }
+        InputEvent::WheelLines(x, y) => {
+            out.push(6);
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+        }
+        InputEvent::WheelPixels(x, y) => {
+            out.push(7);
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+        }
+        InputEvent::CursorEntered => out.push(8),
+        InputEvent::CursorLeft => out.push(9),
+        InputEvent::ImePreedit { text, cursor } => {
+            out.push(10);
+            write_str(out, text);
+            match cursor {
+                Some((start, end)) => {
+                    out.push(1);
+                    write_varint(out, *start as u64);
+                    write_varint(out, *end as u64);
+                }
+                None => out.push(0),
+            }
+        }
+        InputEvent::ImeCommit(text) => {
+            out.push(11);
+            write_str(out, text);
+        }
+    }
+}
+fn read_physical(input: &mut Input) -> std::io::Result<WinitKey> {
+    let index = input.u16()? as usize;
+    Ok(KEY_CODES
+        .get(index)
+        .map_or(WinitKey::Unidentified(NativeKeyCode::Unidentified), |f| {
+            WinitKey::Code(*f)
+        }))
+}
+fn read_logical(input: &mut Input) -> std::io::Result<WinitLogicalKey> {
+    Ok(match input.u8()? {
+        0 => Key::Character(SmolStr::new(input.string()?)),
+        1 => {
+            let index = input.u16()? as usize;
+            NAMED_KEYS
+                .get(index)
+                .map_or(Key::Unidentified(NativeKey::Unidentified), |f| {
+                    Key::Named(*f)
+                })
+        }
+        2 => Key::Dead(char::from_u32(input.u32()?)),
+        3 => Key::Unidentified(NativeKey::Unidentified),
+        _ => return Err(invalid("unknown logical key kind")),
+    })
+}
+fn read_button(input: &mut Input) -> std::io::Result<WinitMouseButton> {
+    Ok(match input.u8()? {
+        0 => WinitMouseButton::Left,
+        1 => WinitMouseButton::Right,
+        2 => WinitMouseButton::Middle,
+        3 => WinitMouseButton::Back,
+        4 => WinitMouseButton::Forward,
+        5 => WinitMouseButton::Other(input.u16()?),
+        _ => return Err(invalid("unknown mouse button")),
+    })
+}
+fn read_event(input: &mut Input) -> std::io::Result<InputEvent> {
+    Ok(match input.u8()? {
+        0 => {
+            let key = read_physical(input)?;
+            let logical = read_logical(input)?;
+            let repeat = input.u8()? != 0;
+            let text = match input.u8()? {
+                0 => None,
+                _ => Some(input.string()?),
+            };
+            InputEvent::KeyPressed {
+                key,
+                logical,
+                text,
+                repeat,
+            }
+        }
+        1 => InputEvent::KeyReleased {
+            key: read_physical(input)?,
+            logical: read_logical(input)?,
+        },
+        2 => {
+            let bits = input.u8()?;
+            InputEvent::ModifiersChanged(Modifiers {
+                shift: bits & 1 != 0,
+                ctrl: bits & 2 != 0,
+                alt: bits & 4 != 0,
+                super_key: bits & 8 != 0,
+            })
+        }
+        3 => InputEvent::MousePressed(read_button(input)?),
+        4 => InputEvent::MouseReleased(read_button(input)?),
+        5 => InputEvent::MouseMoved(input.f32()?, input.f32()?),
+        6 => InputEvent::WheelLines(input.f32()?, input.f32()?),
+        7 => InputEvent::WheelPixels(input.f64()?, input.f64()?),
+        8 => InputEvent::CursorEntered,
+        9 => InputEvent::CursorLeft,
+        10 => {
+            let text = input.string()?;
+            let cursor = match input.u8()? {
+                0 => None,
+                _ => Some((input.varint()? as usize, input.varint()? as usize)),
+            };
+            InputEvent::ImePreedit { text, cursor }
+        }
+        11 => InputEvent::ImeCommit(input.string()?),
+        _ => return Err(invalid("unknown input kind")),
+    })
+}
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::{KeyCode, PhysicalKey};
+
+    use super::*;
+    use crate::harness::{logical_key, tests::harness};
+
+    #[test]
+    fn replaying_a_recording_gives_the_same_frames() {
+        let mut live = harness();
+        let mut recording = InputRecording::new();
+        let press = |key| InputEvent::KeyPressed {
+            key: PhysicalKey::Code(key),
+            logical: logical_key(key, false).0,
+            text: None,
+            repeat: false,
+        };
+        let inputs = [
+            (0, press(KeyCode::ArrowRight)),
+            (2, InputEvent::ImeCommit("ab".to_owned())),
+            (9, InputEvent::ImeCommit("c".to_owned())),
+        ];
+        for frame in 0..12 {
+            for (_, event) in inputs.iter().filter(|f| f.0 == frame) {
+                recording.push(frame, event.clone());
+                live.input(event.clone());
+            }
+            live.step();
+        }
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        let read = InputRecording::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(read, recording);
+
+        let mut replayed = harness();
+        let mut replay = InputReplay::new(read);
+        for frame in 0..12 {
+            for (_, event) in replay.events_for_frame(frame).to_vec() {
+                replayed.input(event);
+            }
+            replayed.step();
+        }
+        assert!(replay.is_finished());
+        assert_eq!(replayed.screen_text(), live.screen_text());
+        assert_eq!(replayed.game.x, live.game.x);
+        replayed.assert_text_at(0, 0, "abc");
+    }
+}
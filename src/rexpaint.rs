@@ -0,0 +1,200 @@
+//! Loading & saving REXPaint images (`.xp` files), the ASCII art editor most roguelikes use
+//! Each layer becomes a `Snapshot`, which is drawn with `Window::apply_snapshot`
+//!
+//! ```ignore
+//! let title = XpImage::load("title.xp")?.flatten();
+//! window.apply_snapshot(&title, 0, 0);
+//! ```
+//! Notes:
+//! - REXPaint's transparent cells (a 255,0,255 background) have a blank char & transparent colors in our snapshots, and the other way around when saving
+//! - REXPaint has no sets, so they are 0 when loading & ignored when saving
+//! - glyphs past 255 (from fonts bigger than CP437) become `?`
+//!
+//! The format is gzipped: a version (i32), the amount of layers (i32), then each layer's width & height (i32s)
+//! followed by its cells, column by column (like `Snapshot`): the glyph (u32), then the fg & bg as RGB bytes
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{
+    binary::{invalid, Input},
+    Snapshot, Window,
+};
+
+// what REXPaint writes as the version
+const VERSION: i32 = -1;
+// the background of transparent cells
+const TRANSPARENT: (u8, u8, u8) = (255, 0, 255);
+
+/// A REXPaint image: its layers, from the bottom one to the top one
+#[derive(Clone)]
+pub struct XpImage {
+    pub layers: Vec<Snapshot>,
+}
+impl XpImage {
+    /// an image with a single layer
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        Self {
+            layers: vec![snapshot],
+        }
+    }
+    /// an image of the whole grid of a window, with a single layer
+    pub fn from_window(window: &Window) -> Self {
+        let (width, height) = window.grid_size();
+        Self::from_snapshot(window.take_snapshot(0, 0, width, height))
+    }
+    /// the size of the image, which is the size of its biggest layer (they are usually all the same)
+    pub fn size(&self) -> (u32, u32) {
+        self.layers.iter().fold((0, 0), |size, layer| {
+            (size.0.max(layer.size.0), size.1.max(layer.size.1))
+        })
+    }
+    /// merges the layers into one: the transparent cells of a layer show the layers below it
+    pub fn flatten(&self) -> Snapshot {
+        let size = self.size();
+        let mut flat = Snapshot::new(size.0, size.1);
+        for layer in &self.layers {
+            for x in 0..layer.size.0 {
+                for y in 0..layer.size.1 {
+                    let from = (y + x * layer.size.1) as usize;
+                    if layer.bg[from * 4 + 3] == 0 {
+                        continue;
+                    }
+                    let to = (y + x * size.1) as usize;
+                    flat.text[to] = layer.text[from];
+                    flat.set[to] = layer.set[from];
+                    flat.fg[to * 4..to * 4 + 4].copy_from_slice(&layer.fg[from * 4..from * 4 + 4]);
+                    flat.bg[to * 4..to * 4 + 4].copy_from_slice(&layer.bg[from * 4..from * 4 + 4]);
+                }
+            }
+        }
+        flat
+    }
+    /// reads an `.xp` file's content
+    pub fn read_from<R: Read>(reader: R) -> std::io::Result<Self> {
+        let mut data = Vec::new();
+        GzDecoder::new(reader).read_to_end(&mut data)?;
+        let mut input = Input {
+            data: &data,
+            pos: 0,
+        };
+        // the version doesn't change anything, REXPaint only ever wrote -1
+        let _version = input.u32()?;
+        let layer_count = input.u32()?;
+        let mut layers = Vec::new();
+        for _ in 0..layer_count {
+            let (width, height) = (input.u32()?, input.u32()?);
+            // each cell takes 10 bytes, so this also keeps absurd sizes from being allocated
+            let cells = (width as usize).checked_mul(height as usize);
+            if cells
+                .and_then(|f| f.checked_mul(10))
+                .is_none_or(|f| f > input.data.len() - input.pos)
+            {
+                return Err(invalid("the file ends too early"));
+            }
+            let mut layer = Snapshot::new(width, height);
+            for index in 0..(width * height) as usize {
+                let glyph = input.u32()?;
+                let [fr, fg, fb, br, bg, bb] = input.array()?;
+                if (br, bg, bb) == TRANSPARENT {
+                    continue;
+                }
+                layer.text[index] = u8::try_from(glyph).unwrap_or(b'?');
+                layer.fg[index * 4..index * 4 + 4].copy_from_slice(&[fr, fg, fb, 255]);
+                layer.bg[index * 4..index * 4 + 4].copy_from_slice(&[br, bg, bb, 255]);
+            }
+            layers.push(layer);
+        }
+        Ok(Self { layers })
+    }
+    /// writes the image as an `.xp` file
+    pub fn write_to<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.layers.len() as u32).to_le_bytes());
+        for layer in &self.layers {
+            out.extend_from_slice(&layer.size.0.to_le_bytes());
+            out.extend_from_slice(&layer.size.1.to_le_bytes());
+            for index in 0..(layer.size.0 * layer.size.1) as usize {
+                let fg = &layer.fg[index * 4..index * 4 + 4];
+                let bg = &layer.bg[index * 4..index * 4 + 4];
+                if bg[3] == 0 {
+                    out.extend_from_slice(&0u32.to_le_bytes());
+                    out.extend_from_slice(&[0, 0, 0, TRANSPARENT.0, TRANSPARENT.1, TRANSPARENT.2]);
+                } else {
+                    out.extend_from_slice(&(layer.text[index] as u32).to_le_bytes());
+                    out.extend_from_slice(&[fg[0], fg[1], fg[2], bg[0], bg[1], bg[2]]);
+                }
+            }
+        }
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        encoder.write_all(&out)?;
+        encoder.finish()?;
+        Ok(())
+    }
+    /// loads an `.xp` file
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::read_from(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+    /// saves the image as an `.xp` file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{snapshot::SnapshotCell, Config};
+
+    #[test]
+    fn xp_images_survive_a_round_trip() {
+        let mut window = Window::headless(Config::default());
+        window.print_at(0, 0, "xp", None, None);
+        // cells with a transparent background are transparent in .xp files, so these ones need one
+        window.set_bg_at(0, 0, (0, 0, 0, 255));
+        window.set_fg_at(0, 0, (10, 20, 30, 255));
+        window.set_bg_at(1, 0, (40, 50, 60, 255));
+        let mut image = XpImage::from_window(&window);
+        // a second layer, transparent except for one cell
+        let (width, height) = image.size();
+        let mut top = Snapshot::new(width, height);
+        let cell = SnapshotCell {
+            ch: b'!',
+            set: 0,
+            fg: (255, 255, 255, 255),
+            bg: (0, 0, 255, 255),
+        };
+        top.set_cell(2, 0, cell);
+        image.layers.push(top);
+
+        let mut bytes = Vec::new();
+        image.write_to(&mut bytes).unwrap();
+        let read = XpImage::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(read.size(), image.size());
+        // .xp colors have no alpha, so only the layer without partly transparent cells comes back as it was
+        assert_eq!(read.layers[1], image.layers[1]);
+        let mut playback = Window::headless(Config::default());
+        playback.apply_snapshot(&read.flatten(), 0, 0);
+        assert_eq!(playback.text_at(0, 0, 3), "xp!");
+        assert_eq!(playback.fg_at(0, 0), Some((10, 20, 30, 255)));
+        assert_eq!(playback.bg_at(1, 0), Some((40, 50, 60, 255)));
+    }
+    #[test]
+    fn huge_layers_are_an_error() {
+        // a file that claims more cells than it has is an error, not a huge allocation
+        let mut header = Vec::new();
+        for value in [VERSION, 1, i32::MAX, i32::MAX] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut gzipped = Vec::new();
+        let mut encoder = GzEncoder::new(&mut gzipped, Compression::default());
+        encoder.write_all(&header).unwrap();
+        encoder.finish().unwrap();
+        assert!(XpImage::read_from(gzipped.as_slice()).is_err());
+    }
+}
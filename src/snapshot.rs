@@ -0,0 +1,389 @@
+//! What can be done with snapshots: saving & loading them (for screens & UI frames that are drawn ahead of time and shipped as assets),
+//! and editing them like small canvases: flipping, rotating, tinting, cropping, & stamping them onto each other
+//!
+//! ```ignore
+//! window.take_snapshot(0, 0, 20, 10).save("frame.yrls", true)?;
+//! let mut frame = Snapshot::load("frame.yrls")?;
+//! frame.flip_horizontal();
+//! window.blit_snapshot(&frame, 4, 4);
+//! ```
+//! Snapshots are column by column: the cell at x, y is at `x * height + y` (see `Snapshot::index`)
+//!
+//! Transparency, for `Window::blit_snapshot` & `Snapshot::composite`: a cell with the char 0 isn't drawn at all,
+//! and a cell with a transparent background (alpha 0) keeps the background under it, so sprites can be drawn on anything
+//!
+//! The file format is binary: a `YRLS` header, a version byte & a flags byte (bit 0 means the rest is zlib compressed),
+//! then the begin & the size (varints), followed by the chars, the sets, the fg & the bg, column by column like in `Snapshot`
+//! Notes:
+//! - files from older versions can always be loaded, files from newer versions can't
+//! - compression is worth it for anything bigger than a few cells, since most screens are mostly empty
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{
+    binary::{invalid, write_varint, Input},
+    Col, Snapshot,
+};
+
+const MAGIC: &[u8; 4] = b"YRLS";
+const VERSION: u8 = 1;
+// the flag for compressed files
+const COMPRESSED: u8 = 1;
+
+/// A cell of a snapshot
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotCell {
+    /// the cp437 char
+    pub ch: u8,
+    pub set: u8,
+    pub fg: Col,
+    pub bg: Col,
+}
+impl SnapshotCell {
+    /// true if the cell isn't drawn when blitting (its char is 0)
+    pub fn is_transparent(&self) -> bool {
+        self.ch == 0
+    }
+}
+impl Snapshot {
+    /// creates a snapshot where every cell is transparent (the char 0 with transparent colors)
+    pub fn new(width: u32, height: u32) -> Self {
+        let cells = (width * height) as usize;
+        Self {
+            begin: (0, 0),
+            size: (width, height),
+            fg: vec![0; cells * 4],
+            bg: vec![0; cells * 4],
+            set: vec![0; cells],
+            text: vec![0; cells],
+        }
+    }
+    /// where the cell at x, y is in `text` & `set` (multiply it by 4 for `fg` & `bg`), None if it's outside
+    pub fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.size.0 && y < self.size.1).then_some((y + x * self.size.1) as usize)
+    }
+    /// returns the cell at x, y (None if it's outside)
+    pub fn cell(&self, x: u32, y: u32) -> Option<SnapshotCell> {
+        let index = self.index(x, y)?;
+        let fg = &self.fg[index * 4..index * 4 + 4];
+        let bg = &self.bg[index * 4..index * 4 + 4];
+        Some(SnapshotCell {
+            ch: self.text[index],
+            set: self.set[index],
+            fg: (fg[0], fg[1], fg[2], fg[3]),
+            bg: (bg[0], bg[1], bg[2], bg[3]),
+        })
+    }
+    /// changes the cell at x, y (nothing happens if it's outside)
+    pub fn set_cell(&mut self, x: u32, y: u32, cell: SnapshotCell) {
+        if let Some(index) = self.index(x, y) {
+            self.text[index] = cell.ch;
+            self.set[index] = cell.set;
+            self.fg[index * 4..index * 4 + 4]
+                .copy_from_slice(&[cell.fg.0, cell.fg.1, cell.fg.2, cell.fg.3]);
+            self.bg[index * 4..index * 4 + 4]
+                .copy_from_slice(&[cell.bg.0, cell.bg.1, cell.bg.2, cell.bg.3]);
+        }
+    }
+    /// returns the part of the snapshot in a region, cut to what's there
+    /// its `begin` is moved by x, y, so it still says where it was taken
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let (x, y) = (x.min(self.size.0), y.min(self.size.1));
+        let width = width.min(self.size.0 - x);
+        let height = height.min(self.size.1 - y);
+        let mut cropped = Self::new(width, height);
+        cropped.begin = (self.begin.0 + x, self.begin.1 + y);
+        for column in 0..width {
+            // columns are contiguous, so they are copied in one go
+            let from = (y + (x + column) * self.size.1) as usize;
+            let to = (column * height) as usize;
+            let len = height as usize;
+            cropped.text[to..to + len].copy_from_slice(&self.text[from..from + len]);
+            cropped.set[to..to + len].copy_from_slice(&self.set[from..from + len]);
+            cropped.fg[to * 4..(to + len) * 4]
+                .copy_from_slice(&self.fg[from * 4..(from + len) * 4]);
+            cropped.bg[to * 4..(to + len) * 4]
+                .copy_from_slice(&self.bg[from * 4..(from + len) * 4]);
+        }
+        cropped
+    }
+    /// mirrors the snapshot left to right (the chars themselves aren't mirrored, a `/` stays a `/`)
+    pub fn flip_horizontal(&mut self) {
+        let (width, height) = self.size;
+        *self = self.remap(width, height, |x, y| (width - 1 - x, y));
+    }
+    /// mirrors the snapshot top to bottom
+    pub fn flip_vertical(&mut self) {
+        let (width, height) = self.size;
+        *self = self.remap(width, height, |x, y| (x, height - 1 - y));
+    }
+    /// turns the snapshot by 90 degrees clockwise, which swaps its width & height (the chars themselves aren't turned)
+    pub fn rotate_clockwise(&mut self) {
+        let (width, height) = self.size;
+        // the new cell at x, y comes from the left column's bottom going up
+        *self = self.remap(height, width, |x, y| (y, height - 1 - x));
+    }
+    /// turns the snapshot by 90 degrees counterclockwise
+    pub fn rotate_counterclockwise(&mut self) {
+        let (width, height) = self.size;
+        *self = self.remap(height, width, |x, y| (width - 1 - y, x));
+    }
+    /// turns the snapshot by 180 degrees
+    pub fn rotate_180(&mut self) {
+        let (width, height) = self.size;
+        *self = self.remap(width, height, |x, y| (width - 1 - x, height - 1 - y));
+    }
+    /// multiplies the colors (fg & bg) by a color, to tint them (white changes nothing), alpha included
+    pub fn tint(&mut self, color: Col) {
+        let color = [color.0, color.1, color.2, color.3];
+        for colors in [&mut self.fg, &mut self.bg] {
+            for (i, channel) in colors.iter_mut().enumerate() {
+                *channel = (*channel as u16 * color[i % 4] as u16 / 255) as u8;
+            }
+        }
+    }
+    /// darkens the colors (fg & bg) by an amount, from 0 (nothing changes) to 1 (black), alpha stays the same
+    pub fn darken(&mut self, amount: f32) {
+        let amount = amount.clamp(0., 1.);
+        let gray = ((1. - amount) * 255.).round() as u8;
+        self.tint((gray, gray, gray, 255));
+    }
+    /// draws another snapshot on this one with its top left corner at x, y, with transparency (see the module's docs)
+    /// what doesn't fit is cut
+    pub fn composite(&mut self, other: &Snapshot, x: i32, y: i32) {
+        for ox in 0..other.size.0 {
+            for oy in 0..other.size.1 {
+                let (Ok(tx), Ok(ty)) = (u32::try_from(x + ox as i32), u32::try_from(y + oy as i32))
+                else {
+                    continue;
+                };
+                let (Some(cell), Some(under)) = (other.cell(ox, oy), self.cell(tx, ty)) else {
+                    continue;
+                };
+                if cell.is_transparent() {
+                    continue;
+                }
+                let bg = if cell.bg.3 == 0 { under.bg } else { cell.bg };
+                self.set_cell(tx, ty, SnapshotCell { bg, ..cell });
+            }
+        }
+    }
+    // builds a snapshot of another size where the cell at x, y comes from `from(x, y)` in this one
+    fn remap<F: Fn(u32, u32) -> (u32, u32)>(&self, width: u32, height: u32, from: F) -> Self {
+        let mut remapped = Self::new(width, height);
+        remapped.begin = self.begin;
+        for x in 0..width {
+            for y in 0..height {
+                let (fx, fy) = from(x, y);
+                if let Some(cell) = self.cell(fx, fy) {
+                    remapped.set_cell(x, y, cell);
+                }
+            }
+        }
+        remapped
+    }
+    /// writes the snapshot in the binary format, zlib compressed if `compressed`
+    /// fails if the buffers don't have the length the size says
+    pub fn write_to<W: Write>(&self, mut writer: W, compressed: bool) -> std::io::Result<()> {
+        let cells = self.size.0 as usize * self.size.1 as usize;
+        if self.text.len() != cells
+            || self.set.len() != cells
+            || self.fg.len() != cells * 4
+            || self.bg.len() != cells * 4
+        {
+            return Err(invalid("the snapshot's buffers don't match its size"));
+        }
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, if compressed { COMPRESSED } else { 0 }])?;
+        let mut body = Vec::with_capacity(cells * 10 + 20);
+        write_varint(&mut body, self.begin.0 as u64);
+        write_varint(&mut body, self.begin.1 as u64);
+        write_varint(&mut body, self.size.0 as u64);
+        write_varint(&mut body, self.size.1 as u64);
+        body.extend_from_slice(&self.text);
+        body.extend_from_slice(&self.set);
+        body.extend_from_slice(&self.fg);
+        body.extend_from_slice(&self.bg);
+        if compressed {
+            let mut encoder = ZlibEncoder::new(writer, Compression::default());
+            encoder.write_all(&body)?;
+            encoder.finish()?;
+        } else {
+            writer.write_all(&body)?;
+        }
+        Ok(())
+    }
+    /// reads a snapshot written by `write_to`
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut header = [0; 6];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a snapshot"));
+        }
+        if header[4] == 0 || header[4] > VERSION {
+            return Err(invalid("unsupported snapshot version"));
+        }
+        let mut body = Vec::new();
+        if header[5] & COMPRESSED != 0 {
+            ZlibDecoder::new(reader).read_to_end(&mut body)?;
+        } else {
+            reader.read_to_end(&mut body)?;
+        }
+        let mut input = Input {
+            data: &body,
+            pos: 0,
+        };
+        let mut coordinate =
+            || u32::try_from(input.varint()?).map_err(|_| invalid("the snapshot is too big"));
+        let begin = (coordinate()?, coordinate()?);
+        let size = (coordinate()?, coordinate()?);
+        let cells = (size.0 as usize)
+            .checked_mul(size.1 as usize)
+            .ok_or_else(|| invalid("the snapshot is too big"))?;
+        // checked before allocating anything, so that a broken size can't take all the memory
+        if cells.saturating_mul(10) > input.data.len() - input.pos {
+            return Err(invalid("the file ends too early"));
+        }
+        Ok(Self {
+            begin,
+            size,
+            text: input.bytes(cells)?.to_vec(),
+            set: input.bytes(cells)?.to_vec(),
+            fg: input.bytes(cells * 4)?.to_vec(),
+            bg: input.bytes(cells * 4)?.to_vec(),
+        })
+    }
+    /// saves the snapshot to a file (see `write_to`)
+    pub fn save<P: AsRef<Path>>(&self, path: P, compressed: bool) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer, compressed)?;
+        writer.flush()
+    }
+    /// loads a snapshot from a file, compressed or not
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::read_from(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Window};
+
+    // a snapshot where every cell is different from the others
+    fn numbered(width: u32, height: u32) -> Snapshot {
+        let mut snapshot = Snapshot::new(width, height);
+        for x in 0..width {
+            for y in 0..height {
+                let cell = SnapshotCell {
+                    ch: (x * height + y + 1) as u8,
+                    set: x as u8,
+                    fg: (x as u8, y as u8, 0, 255),
+                    bg: (0, y as u8, x as u8, 255),
+                };
+                snapshot.set_cell(x, y, cell);
+            }
+        }
+        snapshot
+    }
+    // a 2×1 sprite: an `@` with a transparent background, then a cell that isn't drawn
+    fn sprite() -> Snapshot {
+        let mut sprite = Snapshot::new(2, 1);
+        let cell = SnapshotCell {
+            ch: b'@',
+            set: 0,
+            fg: (255, 0, 0, 255),
+            bg: (0, 0, 0, 0),
+        };
+        sprite.set_cell(0, 0, cell);
+        sprite
+    }
+
+    #[test]
+    fn take_snapshot_reaches_the_edges() {
+        let mut window = Window::headless(Config::default());
+        let (width, height) = window.grid_size();
+        window.set_char_at(width - 1, height - 1, '@');
+        let snapshot = window.take_snapshot(0, 0, width, height);
+        assert_eq!(snapshot.size, (width, height));
+        assert_eq!(snapshot.cell(width - 1, height - 1).unwrap().ch, b'@');
+    }
+    #[test]
+    fn rotations_come_back() {
+        let original = numbered(3, 2);
+        let mut snapshot = original.clone();
+        snapshot.rotate_clockwise();
+        assert_eq!(snapshot.size, (2, 3));
+        // the bottom left corner goes to the top left
+        assert_eq!(snapshot.cell(0, 0), original.cell(0, 1));
+        for _ in 0..3 {
+            snapshot.rotate_clockwise();
+        }
+        assert_eq!(snapshot, original);
+        snapshot.rotate_clockwise();
+        snapshot.rotate_counterclockwise();
+        assert_eq!(snapshot, original);
+        snapshot.rotate_180();
+        snapshot.rotate_180();
+        assert_eq!(snapshot, original);
+    }
+    #[test]
+    fn flips_come_back() {
+        let original = numbered(3, 2);
+        let mut snapshot = original.clone();
+        snapshot.flip_horizontal();
+        assert_eq!(snapshot.cell(0, 0), original.cell(2, 0));
+        snapshot.flip_horizontal();
+        assert_eq!(snapshot, original);
+        snapshot.flip_vertical();
+        snapshot.flip_vertical();
+        assert_eq!(snapshot, original);
+    }
+    #[test]
+    fn crop_is_cut_at_the_edges() {
+        let snapshot = numbered(4, 3);
+        let cropped = snapshot.crop(2, 1, 10, 10);
+        assert_eq!(cropped.size, (2, 2));
+        assert_eq!(cropped.begin, (2, 1));
+        assert_eq!(cropped.cell(0, 0), snapshot.cell(2, 1));
+        assert_eq!(cropped.cell(1, 1), snapshot.cell(3, 2));
+        assert_eq!(snapshot.crop(10, 10, 5, 5).size, (0, 0));
+    }
+    #[test]
+    fn composite_is_transparent() {
+        let original = numbered(3, 3);
+        let mut snapshot = original.clone();
+        snapshot.composite(&sprite(), 1, 1);
+        let cell = snapshot.cell(1, 1).unwrap();
+        assert_eq!(cell.ch, b'@');
+        assert_eq!(cell.fg, (255, 0, 0, 255));
+        assert_eq!(cell.bg, original.cell(1, 1).unwrap().bg);
+        assert_eq!(snapshot.cell(2, 1), original.cell(2, 1));
+        // what doesn't fit is cut
+        snapshot.composite(&sprite(), -1, 2);
+        assert_eq!(snapshot.cell(0, 2), original.cell(0, 2));
+    }
+    #[test]
+    fn blit_is_transparent() {
+        let mut window = Window::headless(Config::default());
+        window.set_bg_at(1, 1, (1, 2, 3, 255));
+        window.set_char_at(2, 1, 'x');
+        window.blit_snapshot(&sprite(), 1, 1);
+        assert_eq!(window.char_at(1, 1), Some('@'));
+        assert_eq!(window.fg_at(1, 1), Some((255, 0, 0, 255)));
+        assert_eq!(window.bg_at(1, 1), Some((1, 2, 3, 255)));
+        assert_eq!(window.char_at(2, 1), Some('x'));
+    }
+    #[test]
+    fn files_round_trip() {
+        let snapshot = numbered(5, 4).crop(1, 1, 3, 3);
+        for compressed in [false, true] {
+            let mut bytes = Vec::new();
+            snapshot.write_to(&mut bytes, compressed).unwrap();
+            assert_eq!(Snapshot::read_from(&bytes[..]).unwrap(), snapshot);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! Serves a `Yarl2Game` over telnet, like the roguelike servers of old: every connection gets its own game
+//! Each session is a `TerminalSession` on its own thread, with the telnet protocol around it:
+//! we ask the client for character mode (it sends every key right away, and we do the echoing, which is to say none)
+//! and for its window size (NAWS), which follows the client's resizes
+//!
+//! ```ignore
+//! // then `telnet 127.0.0.1 2323`
+//! telnet::serve("127.0.0.1:2323", ColorMode::Colors256, || (MyGame::default(), Config::default())).unwrap();
+//! ```
+use std::{
+    io::{self, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{Receiver, TryRecvError},
+        Arc,
+    },
+};
+
+use crate::{
+    terminal::{read_in_background, ColorMode, TerminalSession, END, START},
+    Config, Yarl2Game,
+};
+
+// the telnet commands we use (see RFC 854)
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const IP: u8 = 244;
+const SE: u8 = 240;
+// the options we use
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+// what we ask for when a client connects: we echo (so the client doesn't), no go-aheads (both ways), and the window size
+// together, that's "character mode" for most clients
+const NEGOTIATION: [u8; 12] = [
+    IAC,
+    WILL,
+    ECHO,
+    IAC,
+    WILL,
+    SUPPRESS_GO_AHEAD,
+    IAC,
+    DO,
+    SUPPRESS_GO_AHEAD,
+    IAC,
+    DO,
+    NAWS,
+];
+// the size we assume until the client tells us
+const DEFAULT_SIZE: (u32, u32) = (80, 24);
+// the longest subnegotiation we keep (NAWS takes 5 bytes), the rest is dropped so that a client can't fill our memory
+const MAX_SUB: usize = 16;
+
+/// Listens on `address` (like `"127.0.0.1:2323"`) and runs a game for every connection, each on its own thread
+/// `new_game` makes the game & its config of each session (the game is made on the session's thread, so it doesn't need to be `Send`)
+/// it only returns if we can't listen
+pub fn serve<T, F>(address: impl ToSocketAddrs, mode: ColorMode, new_game: F) -> io::Result<()>
+where
+    T: Yarl2Game,
+    F: Fn() -> (T, Config) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address)?;
+    let new_game = Arc::new(new_game);
+    for stream in listener.incoming() {
+        // a connection that failed doesn't stop the others
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let new_game = new_game.clone();
+        std::thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "someone".to_owned(), |f| f.to_string());
+            let (game, config) = (*new_game)();
+            if let Err(e) = run_session(stream, game, config, mode) {
+                eprintln!("the telnet session of {} ended badly: {}", peer, e);
+            }
+        });
+    }
+    Ok(())
+}
+/// Runs one game over a connection until the game exits or the client leaves (then the game is closed, see `Yarl2Game::close`)
+/// `serve` does this for every connection, but you can accept them yourself
+pub fn run_session<T: Yarl2Game>(
+    stream: TcpStream,
+    game: T,
+    config: Config,
+    mode: ColorMode,
+) -> io::Result<()> {
+    // frames are small & we want them now
+    stream.set_nodelay(true)?;
+    let mut output = stream.try_clone()?;
+    output.write_all(&NEGOTIATION)?;
+    // our output is UTF-8, which never has 255 in it, so it doesn't need escaping
+    output.write_all(START.as_bytes())?;
+    let mut session = TerminalSession::new(game, config, mode);
+    session.resize(DEFAULT_SIZE.0, DEFAULT_SIZE.1);
+    let input = read_in_background(stream.try_clone()?);
+    let result = session_loop(&mut session, &input, &mut output);
+    // we couldn't write to the client, it's as gone as it gets
+    if result.is_err() {
+        session.close();
+    }
+    // the client may already be gone, so these can fail
+    let _ = output.write_all(END.as_bytes());
+    // this also stops the reading thread
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
+// runs the frames of a session until the game exits or the client leaves
+fn session_loop<T: Yarl2Game>(
+    session: &mut TerminalSession<T>,
+    input: &Receiver<Vec<u8>>,
+    output: &mut TcpStream,
+) -> io::Result<()> {
+    let mut decoder = TelnetDecoder::new();
+    loop {
+        let frame_start = crate::now_ms();
+        // everything the client sent since the last frame
+        loop {
+            let bytes = match input.try_recv() {
+                Ok(bytes) => bytes,
+                Err(TryRecvError::Empty) => break,
+                // the client left, so the game ends
+                Err(TryRecvError::Disconnected) => {
+                    session.close();
+                    return Ok(());
+                }
+            };
+            let decoded = decoder.decode(&bytes);
+            output.write_all(&decoded.replies)?;
+            if let Some((columns, rows)) = decoded.size {
+                session.resize(columns, rows);
+            }
+            if session.input_bytes(&decoded.data) {
+                return Ok(());
+            }
+        }
+        if session.step(output)? {
+            return Ok(());
+        }
+        session.wait_for_next_frame(frame_start);
+    }
+}
+/// What some bytes from a telnet client meant
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Decoded {
+    /// what the client typed, with the telnet commands taken out
+    pub data: Vec<u8>,
+    /// the new size of the client's window (columns, rows), if it told us
+    pub size: Option<(u32, u32)>,
+    /// what we must answer (refusals of the options we don't support)
+    pub replies: Vec<u8>,
+}
+// where we are in the telnet stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Data,
+    // after a CR, the LF or NUL that follows isn't another key
+    Cr,
+    // after an IAC
+    Command,
+    // after IAC WILL/WONT/DO/DONT, waiting for the option
+    Option(u8),
+    // inside of a subnegotiation (IAC SB ... IAC SE)
+    Sub,
+    // after an IAC inside of a subnegotiation
+    SubIac,
+}
+/// Takes the telnet protocol out of what a client sends, the commands can be split between reads
+#[derive(Debug)]
+pub struct TelnetDecoder {
+    state: State,
+    // the subnegotiation being read
+    sub: Vec<u8>,
+}
+impl TelnetDecoder {
+    /// creates a decoder for a new connection
+    pub fn new() -> Self {
+        Self {
+            state: State::Data,
+            sub: Vec::new(),
+        }
+    }
+    /// decodes the bytes that were just read
+    pub fn decode(&mut self, bytes: &[u8]) -> Decoded {
+        let mut decoded = Decoded::default();
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Data | State::Cr, IAC) => State::Command,
+                // clients send enter as CR LF or CR NUL, we only want one key
+                (State::Cr, b'\n' | 0) => State::Data,
+                (State::Data | State::Cr, b'\r') => {
+                    decoded.data.push(byte);
+                    State::Cr
+                }
+                (State::Data | State::Cr, _) => {
+                    decoded.data.push(byte);
+                    State::Data
+                }
+                // an escaped 255
+                (State::Command, IAC) => {
+                    decoded.data.push(IAC);
+                    State::Data
+                }
+                (State::Command, WILL | WONT | DO | DONT) => State::Option(byte),
+                (State::Command, SB) => {
+                    self.sub.clear();
+                    State::Sub
+                }
+                // "interrupt process" is Ctrl+C
+                (State::Command, IP) => {
+                    decoded.data.push(0x03);
+                    State::Data
+                }
+                // the other commands (go ahead, no-op...) don't mean anything to us
+                (State::Command, _) => State::Data,
+                (State::Option(command), option) => {
+                    // we say no to whatever we didn't ask for, the answers to our own requests need no answer
+                    if ![ECHO, SUPPRESS_GO_AHEAD, NAWS].contains(&option) {
+                        match command {
+                            WILL => decoded.replies.extend([IAC, DONT, option]),
+                            DO => decoded.replies.extend([IAC, WONT, option]),
+                            _ => {}
+                        }
+                    }
+                    State::Data
+                }
+                (State::Sub, IAC) => State::SubIac,
+                (State::Sub, _) => {
+                    self.push_sub(byte);
+                    State::Sub
+                }
+                (State::SubIac, SE) => {
+                    if let [NAWS, w1, w0, h1, h0] = self.sub[..] {
+                        let size = (
+                            u16::from_be_bytes([w1, w0]) as u32,
+                            u16::from_be_bytes([h1, h0]) as u32,
+                        );
+                        // some clients say 0 when they don't know
+                        if size.0 > 0 && size.1 > 0 {
+                            decoded.size = Some(size);
+                        }
+                    }
+                    State::Data
+                }
+                // 255 is escaped in subnegotiations too
+                (State::SubIac, IAC) => {
+                    self.push_sub(IAC);
+                    State::Sub
+                }
+                (State::SubIac, _) => State::Data,
+            };
+        }
+        decoded
+    }
+    // adds a byte to the subnegotiation, unless it's too long already (it can't be one we understand then)
+    fn push_sub(&mut self, byte: u8) {
+        if self.sub.len() < MAX_SUB {
+            self.sub.push(byte);
+        }
+    }
+}
+impl Default for TelnetDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endless_subnegotiations_stay_small() {
+        let mut decoder = TelnetDecoder::new();
+        decoder.decode(&[IAC, SB, NAWS]);
+        for _ in 0..1000 {
+            decoder.decode(&[0; 1000]);
+            decoder.decode(&[IAC, IAC]);
+        }
+        assert_eq!(decoder.sub.len(), MAX_SUB);
+        // it's too long to be a window size, & what comes after it is read as usual
+        let decoded = decoder.decode(&[IAC, SE, b'a']);
+        assert_eq!(decoded.size, None);
+        assert_eq!(decoded.data, b"a");
+    }
+    #[test]
+    fn window_sizes() {
+        let mut decoder = TelnetDecoder::new();
+        // 200 columns & 50 rows, split between two reads
+        assert_eq!(decoder.decode(&[IAC, SB, NAWS, 0, 200]), Decoded::default());
+        let decoded = decoder.decode(&[0, 50, IAC, SE, b'x']);
+        assert_eq!(decoded.size, Some((200, 50)));
+        assert_eq!(decoded.data, b"x");
+        // some clients say 0 when they don't know
+        assert_eq!(
+            decoder.decode(&[IAC, SB, NAWS, 0, 0, 0, 0, IAC, SE]).size,
+            None
+        );
+    }
+    #[test]
+    fn escaped_iacs() {
+        let mut decoder = TelnetDecoder::new();
+        assert_eq!(
+            decoder.decode(&[b'a', IAC, IAC, b'b']).data,
+            [b'a', IAC, b'b']
+        );
+        // 255 columns
+        let decoded = decoder.decode(&[IAC, SB, NAWS, 0, IAC, IAC, 0, 24, IAC, SE]);
+        assert_eq!(decoded.size, Some((255, 24)));
+        assert!(decoded.data.is_empty());
+    }
+    #[test]
+    fn options_we_dont_know_are_refused() {
+        let mut decoder = TelnetDecoder::new();
+        // the answers to what we asked for need no answer
+        let decoded = decoder.decode(&[IAC, DO, ECHO, IAC, WILL, NAWS, IAC, DO, SUPPRESS_GO_AHEAD]);
+        assert_eq!(decoded, Decoded::default());
+        // terminal type (24) & linemode (34)
+        let decoded = decoder.decode(&[IAC, WILL, 24, IAC, DO, 34, IAC, WONT, 5]);
+        assert_eq!(decoded.replies, [IAC, DONT, 24, IAC, WONT, 34]);
+        assert!(decoded.data.is_empty());
+    }
+    #[test]
+    fn enter_is_one_key() {
+        let mut decoder = TelnetDecoder::new();
+        assert_eq!(decoder.decode(b"a\r\nb\r").data, b"a\rb\r");
+        assert_eq!(decoder.decode(&[0, IAC, IP]).data, [0x03]);
+    }
+}
@@ -0,0 +1,938 @@
+//! Runs a `Yarl2Game` in a terminal (over SSH, in a plain console...) instead of a window
+//! The grid is drawn with ANSI escape sequences, and only the cells that changed since the last frame are sent
+//! The keys & the mouse are read from stdin, and go through `NiceKeyboard` like the window's inputs
+//!
+//! ```ignore
+//! terminal::run_terminal(MyGame::default(), Config::default()).unwrap();
+//! ```
+//! Notes:
+//! - terminals have one font, so the sets are ignored, and the chars are turned into unicode with `u8_to_ch`
+//! - terminals don't tell us when keys are released, so every key is pressed & released right away (`keys` stays empty, use the just_pressed sets)
+//! - the instances are drawn on the cell they are the closest to
+//! - a lone Escape can only be told apart from the start of a sequence by waiting, so it arrives one frame late
+//!
+//! The pieces (`AnsiRenderer`, `InputParser` & `TerminalSession`) work on bytes, so they can also drive other streams than stdin & stdout (see the `telnet` module)
+use std::{fmt::Write as _, io::Write};
+
+use winit::keyboard::{Key, KeyCode, PhysicalKey, SmolStr};
+
+use crate::{
+    colors::VGA_PALETTE,
+    feed_input,
+    harness::{logical_key, physical_key},
+    u8_to_ch, Col, Config, InputEvent, Modifiers, NiceKeyboard, Window, WinitMouseButton,
+    Yarl2Game,
+};
+
+/// switches to the alternate screen, hides the cursor & turns mouse reporting on (SGR mode, with the movements)
+pub const START: &str = "\x1b[?1049h\x1b[?25l\x1b[?1003h\x1b[?1006h";
+/// undoes `START`
+pub const END: &str = "\x1b[0m\x1b[?1006l\x1b[?1003l\x1b[?25h\x1b[?1049l";
+// the time (in ms) between two frames, terminals don't need more than 30 frames per second
+const FRAME_TIME: f64 = 1000. / 30.;
+
+/// How many colors the terminal can show, the colors of the grid are rounded to the closest one it has
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 24-bit colors, the colors are sent as they are
+    TrueColor,
+    /// the xterm palette: a 6×6×6 cube & 24 grays
+    Colors256,
+    /// the 16 colors of old terminals (we assume the VGA ones)
+    Colors16,
+}
+impl ColorMode {
+    /// guesses what the terminal supports from `COLORTERM` & `TERM`, like most terminal apps do
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm.contains("truecolor")
+            || colorterm.contains("24bit")
+            || std::env::var_os("WT_SESSION").is_some()
+        {
+            ColorMode::TrueColor
+        } else if term.contains("256color") {
+            ColorMode::Colors256
+        } else {
+            ColorMode::Colors16
+        }
+    }
+    // the SGR parameters that set a color, `background` picks 48 over 38
+    fn sgr(self, color: (u8, u8, u8), background: bool) -> String {
+        match self {
+            ColorMode::TrueColor => format!(
+                "{};2;{};{};{}",
+                if background { 48 } else { 38 },
+                color.0,
+                color.1,
+                color.2
+            ),
+            ColorMode::Colors256 => {
+                format!("{};5;{}", if background { 48 } else { 38 }, to_256(color))
+            }
+            ColorMode::Colors16 => {
+                let index = to_16(color);
+                let base = if background { 40 } else { 30 };
+                // the bright colors have their own codes
+                if index < 8 {
+                    (base + index).to_string()
+                } else {
+                    (base + 60 + index - 8).to_string()
+                }
+            }
+        }
+    }
+}
+// the levels of each channel in the xterm color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+// the closest color of the 256 color palette (we skip the first 16, since terminals often change them)
+fn to_256(color: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| {
+        (0..6)
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - value as i32).abs())
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (level(color.0), level(color.1), level(color.2));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+    // the grays go from 8 to 238, by 10
+    let average = (color.0 as u32 + color.1 as u32 + color.2 as u32) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + gray_index * 10;
+    if distance(color, (gray, gray, gray)) < distance(color, cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+// the closest color of the 16 color palette
+fn to_16(color: (u8, u8, u8)) -> u8 {
+    (0..16)
+        .min_by_key(|i| {
+            let vga = VGA_PALETTE[*i];
+            distance(color, (vga.0, vga.1, vga.2))
+        })
+        .unwrap_or(0) as u8
+}
+// what a terminal cell shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TermCell {
+    ch: char,
+    fg: (u8, u8, u8),
+    bg: (u8, u8, u8),
+}
+// `top` over `bottom`, terminals have no transparency
+fn over(top: Col, bottom: (u8, u8, u8)) -> (u8, u8, u8) {
+    let alpha = top.3 as u32;
+    let channel =
+        |top: u8, bottom: u8| ((top as u32 * alpha + bottom as u32 * (255 - alpha)) / 255) as u8;
+    (
+        channel(top.0, bottom.0),
+        channel(top.1, bottom.1),
+        channel(top.2, bottom.2),
+    )
+}
+// the chars terminals can't show (CP437's 0 is a control char in unicode) are blank instead
+fn printable(ch: char) -> char {
+    if ch.is_control() {
+        ' '
+    } else {
+        ch
+    }
+}
+// what each cell of the grid looks like in a terminal, instances included
+fn cells(window: &Window) -> Vec<TermCell> {
+    let (width, height) = window.config_chargrid.size;
+    let background = window.background_color;
+    let background = (background.0, background.1, background.2);
+    let color = |buffer: &[u8], index: usize| {
+        let n = &buffer[index * 4..index * 4 + 4];
+        (n[0], n[1], n[2], n[3])
+    };
+    let mut cells: Vec<TermCell> = (0..(width * height) as usize)
+        .map(|index| {
+            let bg = over(color(&window.buffer_colors_bg, index), background);
+            TermCell {
+                ch: printable(u8_to_ch(window.buffer_chars[index])),
+                fg: over(color(&window.buffer_colors_fg, index), bg),
+                bg,
+            }
+        })
+        .collect();
+    for instance in window.instances() {
+        let (x, y) = (instance.position[0].round(), instance.position[1].round());
+        if x < 0. || y < 0. || x >= width as f32 || y >= height as f32 {
+            continue;
+        }
+        let cell = &mut cells[(x as u32 + y as u32 * width) as usize];
+        cell.bg = over(instance.bg, cell.bg);
+        let ch = printable(u8_to_ch(instance.set_char[1]));
+        // a blank instance only colors the cell, like it would on the GPU
+        if ch != ' ' {
+            cell.ch = ch;
+            cell.fg = over(instance.fg, cell.bg);
+        }
+    }
+    cells
+}
+/// Draws the grid in a terminal with escape sequences, only sending the cells that changed since the last frame
+pub struct AnsiRenderer {
+    /// how the colors are sent (call `invalidate` after changing it, so that the whole grid gets them)
+    pub mode: ColorMode,
+    // what the terminal shows, None when everything must be redrawn
+    shown: Option<Vec<TermCell>>,
+    // the size of the terminal in cells, the grid is cut to it
+    viewport: (u32, u32),
+}
+impl AnsiRenderer {
+    /// creates a renderer, the first frame redraws everything
+    pub fn new(mode: ColorMode) -> Self {
+        Self {
+            mode,
+            shown: None,
+            viewport: (u32::MAX, u32::MAX),
+        }
+    }
+    /// the terminal changed size (in cells), the next frame redraws everything
+    pub fn resize(&mut self, columns: u32, rows: u32) {
+        self.viewport = (columns, rows);
+        self.invalidate();
+    }
+    /// forgets what the terminal shows, so that the next frame redraws everything (if something else drew in the terminal, for instance)
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+    /// returns the escape sequences that turn the last frame into this one
+    /// it's empty if nothing changed
+    pub fn render(&mut self, window: &Window) -> String {
+        let cells = cells(window);
+        let (width, height) = window.config_chargrid.size;
+        let (columns, rows) = (width.min(self.viewport.0), height.min(self.viewport.1));
+        // a new grid size is as good as a new terminal
+        let shown = self
+            .shown
+            .as_ref()
+            .filter(|shown| shown.len() == cells.len());
+        let mut out = String::new();
+        if shown.is_none() {
+            out.push_str("\x1b[0m\x1b[2J");
+        }
+        // where the terminal's cursor is, & the colors it writes with (None if we don't know)
+        let mut cursor = None;
+        let mut colors = None;
+        for y in 0..rows {
+            for x in 0..columns {
+                let index = (x + y * width) as usize;
+                let cell = cells[index];
+                if shown.is_some_and(|shown| shown[index] == cell) {
+                    continue;
+                }
+                if cursor != Some((x, y)) {
+                    let _ = write!(out, "\x1b[{};{}H", y + 1, x + 1);
+                }
+                if colors != Some((cell.fg, cell.bg)) {
+                    let _ = write!(
+                        out,
+                        "\x1b[{};{}m",
+                        self.mode.sgr(cell.fg, false),
+                        self.mode.sgr(cell.bg, true)
+                    );
+                    colors = Some((cell.fg, cell.bg));
+                }
+                out.push(cell.ch);
+                // the cursor stays on the last column instead of moving past it
+                cursor = (x + 1 < self.viewport.0).then_some((x + 1, y));
+            }
+        }
+        if colors.is_some() {
+            out.push_str("\x1b[0m");
+        }
+        self.shown = Some(cells);
+        out
+    }
+}
+// a key, as the terminal gives it
+enum TermKey {
+    Code(KeyCode),
+    Char(char),
+}
+// what a sequence of bytes means
+enum Token {
+    Key(TermKey, Modifiers),
+    // an SGR mouse report: the button code, the cell (from 0) & M (pressed/moved) or m (released)
+    Mouse {
+        code: u32,
+        x: u32,
+        y: u32,
+        pressed: bool,
+    },
+    // a sequence we don't know, or a char we can't use
+    Nothing,
+}
+/// Turns what a terminal sends (text, escape sequences & SGR mouse reports) into `InputEvent`s
+#[derive(Debug, Default)]
+pub struct InputParser {
+    // the start of a sequence that hasn't fully arrived yet
+    pending: Vec<u8>,
+    // if the mouse was reported yet (the first report is when it enters)
+    mouse_seen: bool,
+}
+impl InputParser {
+    /// creates a parser with nothing pending
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// parses bytes, an incomplete sequence at the end is kept for the next call
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<InputEvent> {
+        self.pending.extend_from_slice(bytes);
+        self.parse(false)
+    }
+    /// parses what's left as it is: a lone ESC is the Escape key then
+    /// call it when nothing came for a while
+    pub fn flush(&mut self) -> Vec<InputEvent> {
+        self.parse(true)
+    }
+    /// returns true if the end of a sequence is still awaited
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+    fn parse(&mut self, flush: bool) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        let mut start = 0;
+        while start < self.pending.len() {
+            let Some((len, token)) = parse_token(&self.pending[start..], flush) else {
+                break;
+            };
+            start += len;
+            self.push_events(token, &mut events);
+        }
+        self.pending.drain(..start);
+        events
+    }
+    // turns a token into inputs
+    fn push_events(&mut self, token: Token, events: &mut Vec<InputEvent>) {
+        match token {
+            Token::Nothing => {}
+            Token::Key(key, mut modifiers) => {
+                let (key, logical, text) = match key {
+                    TermKey::Code(code) => {
+                        let (logical, text) = logical_key(code, modifiers.shift);
+                        (PhysicalKey::Code(code), logical, text)
+                    }
+                    TermKey::Char(ch) => {
+                        // uppercase letters are typed with shift, this makes "Shift+KeyA" work
+                        modifiers.shift |= ch.is_uppercase();
+                        (
+                            physical_key(ch),
+                            Key::Character(SmolStr::new(ch.encode_utf8(&mut [0; 4]))),
+                            Some(ch.to_string()),
+                        )
+                    }
+                };
+                // with ctrl or alt, it's a shortcut rather than text
+                let text = text.filter(|_| !modifiers.ctrl && !modifiers.alt);
+                let held = modifiers != Modifiers::default();
+                if held {
+                    events.push(InputEvent::ModifiersChanged(modifiers));
+                }
+                events.push(InputEvent::KeyPressed {
+                    key,
+                    logical: logical.clone(),
+                    text,
+                    repeat: false,
+                });
+                // we never hear about releases, so the key is released right away
+                events.push(InputEvent::KeyReleased { key, logical });
+                if held {
+                    events.push(InputEvent::ModifiersChanged(Modifiers::default()));
+                }
+            }
+            Token::Mouse {
+                code,
+                x,
+                y,
+                pressed,
+            } => {
+                if !self.mouse_seen {
+                    self.mouse_seen = true;
+                    events.push(InputEvent::CursorEntered);
+                }
+                // the terminal only knows the cell, so we say the middle of it
+                events.push(InputEvent::MouseMoved(x as f32 + 0.5, y as f32 + 0.5));
+                if code & 64 != 0 {
+                    // the wheel: up, down, left & right
+                    let (x, y) = match code & 3 {
+                        0 => (0., 1.),
+                        1 => (0., -1.),
+                        2 => (1., 0.),
+                        _ => (-1., 0.),
+                    };
+                    events.push(InputEvent::WheelLines(x, y));
+                } else if code & 32 == 0 {
+                    // not a movement, so a button changed
+                    let button = match code & 3 {
+                        0 => WinitMouseButton::Left,
+                        1 => WinitMouseButton::Middle,
+                        2 => WinitMouseButton::Right,
+                        _ => return,
+                    };
+                    events.push(if pressed {
+                        InputEvent::MousePressed(button)
+                    } else {
+                        InputEvent::MouseReleased(button)
+                    });
+                }
+            }
+        }
+    }
+}
+// parses the token at the start of the bytes, with its length
+// None if it isn't complete yet (with `flush`, it always returns something)
+fn parse_token(bytes: &[u8], flush: bool) -> Option<(usize, Token)> {
+    let ctrl = Modifiers {
+        ctrl: true,
+        ..Modifiers::default()
+    };
+    let key = |code| Token::Key(TermKey::Code(code), Modifiers::default());
+    match bytes[0] {
+        0x1b => parse_escape(bytes, flush),
+        b'\r' | b'\n' => Some((1, key(KeyCode::Enter))),
+        b'\t' => Some((1, key(KeyCode::Tab))),
+        0x7f | 0x08 => Some((1, key(KeyCode::Backspace))),
+        0x00 => Some((1, Token::Key(TermKey::Code(KeyCode::Space), ctrl))),
+        // ctrl + a letter gives its place in the alphabet
+        byte @ 0x01..=0x1a => Some((
+            1,
+            Token::Key(TermKey::Char((b'a' + byte - 1) as char), ctrl),
+        )),
+        0x1c..=0x1f => Some((1, Token::Nothing)),
+        byte => {
+            let len = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if bytes.len() < len {
+                return flush.then_some((bytes.len(), Token::Nothing));
+            }
+            let ch = std::str::from_utf8(&bytes[..len])
+                .ok()
+                .and_then(|f| f.chars().next());
+            Some((
+                len,
+                ch.map_or(Token::Nothing, |ch| {
+                    Token::Key(TermKey::Char(ch), Modifiers::default())
+                }),
+            ))
+        }
+    }
+}
+// parses what starts with ESC: CSI & SS3 sequences, alt + a key, or the Escape key itself
+fn parse_escape(bytes: &[u8], flush: bool) -> Option<(usize, Token)> {
+    let escape = || {
+        Some((
+            1,
+            Token::Key(TermKey::Code(KeyCode::Escape), Modifiers::default()),
+        ))
+    };
+    let Some(&next) = bytes.get(1) else {
+        return if flush { escape() } else { None };
+    };
+    match next {
+        b'[' => {
+            // the parameters go on until a byte in @..~
+            let Some(end) = bytes
+                .iter()
+                .skip(2)
+                .position(|b| (0x40..=0x7e).contains(b))
+                .map(|f| f + 2)
+            else {
+                return if flush { escape() } else { None };
+            };
+            let params = std::str::from_utf8(&bytes[2..end]).unwrap_or("");
+            Some((end + 1, csi(params, bytes[end])))
+        }
+        b'O' => {
+            let Some(&last) = bytes.get(2) else {
+                return if flush { escape() } else { None };
+            };
+            Some((3, ss3(last)))
+        }
+        // nothing starts with two escapes, so the first one was the key
+        0x1b => escape(),
+        _ => {
+            // alt + a key
+            let (len, token) = parse_token(&bytes[1..], flush)?;
+            let token = match token {
+                Token::Key(key, modifiers) => Token::Key(
+                    key,
+                    Modifiers {
+                        alt: true,
+                        ..modifiers
+                    },
+                ),
+                other => other,
+            };
+            Some((len + 1, token))
+        }
+    }
+}
+// the modifiers of a CSI sequence (the second parameter), it's 1 + a bitmask
+fn modifiers(param: u32) -> Modifiers {
+    let bits = param.saturating_sub(1);
+    Modifiers {
+        shift: bits & 1 != 0,
+        alt: bits & 2 != 0,
+        ctrl: bits & 4 != 0,
+        super_key: bits & 8 != 0,
+    }
+}
+// CSI sequences: ESC [ parameters final-byte
+fn csi(params: &str, last: u8) -> Token {
+    if let Some(mouse) = params.strip_prefix('<') {
+        let mut numbers = mouse.split(';').map(|f| f.parse::<u32>().unwrap_or(0));
+        let (code, x, y) = (
+            numbers.next().unwrap_or(0),
+            numbers.next().unwrap_or(1),
+            numbers.next().unwrap_or(1),
+        );
+        return Token::Mouse {
+            code,
+            x: x.saturating_sub(1),
+            y: y.saturating_sub(1),
+            pressed: last == b'M',
+        };
+    }
+    let numbers: Vec<u32> = params.split(';').map(|f| f.parse().unwrap_or(0)).collect();
+    let modifiers = numbers
+        .get(1)
+        .map_or(Modifiers::default(), |f| modifiers(*f));
+    let code = match last {
+        b'A' => KeyCode::ArrowUp,
+        b'B' => KeyCode::ArrowDown,
+        b'C' => KeyCode::ArrowRight,
+        b'D' => KeyCode::ArrowLeft,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F1,
+        b'Q' => KeyCode::F2,
+        b'R' => KeyCode::F3,
+        b'S' => KeyCode::F4,
+        b'Z' => {
+            return Token::Key(
+                TermKey::Code(KeyCode::Tab),
+                Modifiers {
+                    shift: true,
+                    ..modifiers
+                },
+            )
+        }
+        b'~' => match numbers[0] {
+            1 | 7 => KeyCode::Home,
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            4 | 8 => KeyCode::End,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            11 => KeyCode::F1,
+            12 => KeyCode::F2,
+            13 => KeyCode::F3,
+            14 => KeyCode::F4,
+            15 => KeyCode::F5,
+            17 => KeyCode::F6,
+            18 => KeyCode::F7,
+            19 => KeyCode::F8,
+            20 => KeyCode::F9,
+            21 => KeyCode::F10,
+            23 => KeyCode::F11,
+            24 => KeyCode::F12,
+            _ => return Token::Nothing,
+        },
+        _ => return Token::Nothing,
+    };
+    Token::Key(TermKey::Code(code), modifiers)
+}
+// SS3 sequences: ESC O final-byte, some terminals send them for the arrows & F1-F4
+fn ss3(last: u8) -> Token {
+    let code = match last {
+        b'A' => KeyCode::ArrowUp,
+        b'B' => KeyCode::ArrowDown,
+        b'C' => KeyCode::ArrowRight,
+        b'D' => KeyCode::ArrowLeft,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F1,
+        b'Q' => KeyCode::F2,
+        b'R' => KeyCode::F3,
+        b'S' => KeyCode::F4,
+        _ => return Token::Nothing,
+    };
+    Token::Key(TermKey::Code(code), Modifiers::default())
+}
+/// A game running in a terminal: it eats the bytes the terminal sends, and gives back the bytes that draw its frames
+pub struct TerminalSession<T: Yarl2Game> {
+    pub game: T,
+    pub window: Window<'static>,
+    pub keyboard: NiceKeyboard,
+    pub renderer: AnsiRenderer,
+    pub parser: InputParser,
+    /// the time (in ms) between two frames
+    /// Default: 1000/30
+    pub frame_time: f64,
+    // true if bytes came since the last frame, a sequence may still be arriving then
+    fresh_input: bool,
+}
+impl<T: Yarl2Game> TerminalSession<T> {
+    /// creates the session, with a headless window (see `Window::headless`)
+    pub fn new(game: T, config: Config, mode: ColorMode) -> Self {
+        Self {
+            game,
+            keyboard: NiceKeyboard::for_config(&config),
+            window: Window::headless(config),
+            renderer: AnsiRenderer::new(mode),
+            parser: InputParser::new(),
+            frame_time: FRAME_TIME,
+            fresh_input: false,
+        }
+    }
+    /// feeds the bytes the terminal sent, the inputs will be seen by the next `step`
+    /// returns true if the game was closed: Ctrl+C asks it to close (see `Yarl2Game::close_requested`), since raw terminals leave that to us
+    pub fn input_bytes(&mut self, bytes: &[u8]) -> bool {
+        self.fresh_input = true;
+        let events = self.parser.feed(bytes);
+        self.input_events(events)
+    }
+    fn input_events(&mut self, events: Vec<InputEvent>) -> bool {
+        for event in events {
+            let interrupt = self.keyboard.modifiers.ctrl
+                && matches!(
+                    event,
+                    InputEvent::KeyPressed {
+                        key: PhysicalKey::Code(KeyCode::KeyC),
+                        ..
+                    }
+                );
+            feed_input(&mut self.game, &mut self.window, &mut self.keyboard, event);
+            if interrupt && self.game.close_requested(&mut self.window) {
+                self.close();
+                return true;
+            }
+        }
+        false
+    }
+    // the game is over: it's closed & its grid recording is saved (see `Config::record_grid`)
+    pub(crate) fn close(&mut self) {
+        self.game.close();
+        self.window.save_grid_recording();
+    }
+    /// the terminal changed size (in cells)
+    pub fn resize(&mut self, columns: u32, rows: u32) {
+        self.renderer.resize(columns, rows);
+    }
+    // sleeps until it's time for the next frame, `frame_start` is when the current one started (from `now_ms`)
+    pub(crate) fn wait_for_next_frame(&self, frame_start: f64) {
+        let elapsed = crate::now_ms() - frame_start;
+        if elapsed < self.frame_time {
+            std::thread::sleep(std::time::Duration::from_secs_f64(
+                (self.frame_time - elapsed) / 1000.,
+            ));
+        }
+    }
+    /// runs one frame, the same way the event loop does, and writes what changed to `output`
+    /// returns true if the game asked to exit (`close` has been called then)
+    pub fn step<W: Write>(&mut self, output: &mut W) -> std::io::Result<bool> {
+        // nothing came for a whole frame, so what's left isn't the start of a sequence
+        if !self.fresh_input && self.parser.has_pending() {
+            let events = self.parser.flush();
+            if self.input_events(events) {
+                return Ok(true);
+            }
+        }
+        self.fresh_input = false;
+        self.keyboard.update_repeats(crate::now_ms());
+        self.game.pre_draw(&mut self.window, &self.keyboard);
+        // there is no GPU, so this only resets the dirty flag (and records the grid, see `Config::record_grid`)
+        let _ = self.window.draw();
+        output.write_all(self.renderer.render(&self.window).as_bytes())?;
+        output.flush()?;
+        self.keyboard.end_frame();
+        self.game.post_draw();
+        if self.game.should_exit() {
+            self.close();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+/// Runs the game in the terminal of this process until it exits, with a frame every `TerminalSession::frame_time`
+/// the terminal is put in raw mode (with `stty`) & switched to its alternate screen, both are undone at the end (even on errors)
+#[cfg(unix)]
+pub fn run_terminal<T: Yarl2Game>(game: T, config: Config) -> std::io::Result<()> {
+    let mut session = TerminalSession::new(game, config, ColorMode::detect());
+    let _raw = RawTerminal::enter()?;
+    let mut size = terminal_size().unwrap_or((80, 24));
+    session.resize(size.0, size.1);
+    let input = read_in_background(std::io::stdin());
+    let mut stdout = std::io::stdout().lock();
+    let mut last_size_check = crate::now_ms();
+    loop {
+        let frame_start = crate::now_ms();
+        // everything that was typed since the last frame
+        while let Ok(bytes) = input.try_recv() {
+            if session.input_bytes(&bytes) {
+                return Ok(());
+            }
+        }
+        if session.step(&mut stdout)? {
+            return Ok(());
+        }
+        // we would need signals to hear about resizes, so we ask every half second instead
+        if frame_start - last_size_check > 500. {
+            last_size_check = frame_start;
+            if let Some(new_size) = terminal_size().filter(|f| *f != size) {
+                size = new_size;
+                session.resize(size.0, size.1);
+            }
+        }
+        session.wait_for_next_frame(frame_start);
+    }
+}
+// puts the terminal in raw mode & on the alternate screen, and puts it back the way it was when dropped
+#[cfg(unix)]
+struct RawTerminal {
+    // what `stty -g` said before we changed anything
+    saved: String,
+}
+#[cfg(unix)]
+impl RawTerminal {
+    fn enter() -> std::io::Result<Self> {
+        let saved = stty(&["-g"])?.trim().to_owned();
+        stty(&["raw", "-echo"])?;
+        let mut stdout = std::io::stdout();
+        stdout.write_all(START.as_bytes())?;
+        stdout.flush()?;
+        Ok(Self { saved })
+    }
+}
+#[cfg(unix)]
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(END.as_bytes());
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+// runs stty on our terminal (it works on its stdin), returns what it printed
+#[cfg(unix)]
+fn stty(args: &[&str]) -> std::io::Result<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other("stdin isn't a terminal"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+// the size of the terminal in cells (columns, rows)
+#[cfg(unix)]
+fn terminal_size() -> Option<(u32, u32)> {
+    let size = stty(&["size"]).ok()?;
+    let mut numbers = size.split_whitespace().map(|f| f.parse::<u32>().ok());
+    let (rows, columns) = (numbers.next()??, numbers.next()??);
+    Some((columns, rows))
+}
+// reads on another thread, since reading blocks, and sends what it read through a channel
+// the thread stops when the reader ends or the receiver is dropped (after its next read)
+pub(crate) fn read_in_background<R: std::io::Read + Send + 'static>(
+    mut reader: R,
+) -> std::sync::mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = [0; 1024];
+        while let Ok(len @ 1..) = reader.read(&mut buffer) {
+            if sender.send(buffer[..len].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: Modifiers = Modifiers {
+        shift: false,
+        ctrl: true,
+        alt: false,
+        super_key: false,
+    };
+    // the keys that were pressed, with their text
+    fn presses(events: &[InputEvent]) -> Vec<(PhysicalKey, Option<String>)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                InputEvent::KeyPressed { key, text, .. } => Some((*key, text.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+    // the text of the output, without the escape sequences
+    fn visible(out: &str) -> String {
+        let mut text = String::new();
+        let mut chars = out.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\x1b' {
+                // skips the `[`, the parameters & the final byte
+                chars.find(|f| f.is_ascii_alphabetic());
+            } else {
+                text.push(ch);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn csi_keys() {
+        let mut parser = InputParser::new();
+        let events = parser.feed(b"\x1b[A\x1b[3~\x1bOP");
+        assert_eq!(
+            presses(&events),
+            vec![
+                (PhysicalKey::Code(KeyCode::ArrowUp), None),
+                (PhysicalKey::Code(KeyCode::Delete), None),
+                (PhysicalKey::Code(KeyCode::F1), None),
+            ]
+        );
+        // the modifiers are only held for the key
+        let events = parser.feed(b"\x1b[1;5C");
+        assert_eq!(events[0], InputEvent::ModifiersChanged(CTRL));
+        assert_eq!(
+            presses(&events)[0].0,
+            PhysicalKey::Code(KeyCode::ArrowRight)
+        );
+        assert_eq!(
+            events.last(),
+            Some(&InputEvent::ModifiersChanged(Modifiers::default()))
+        );
+        // text, & ctrl + a letter (which types nothing)
+        let events = parser.feed(b"a\x13");
+        assert_eq!(presses(&events)[0].1.as_deref(), Some("a"));
+        assert_eq!(events[2], InputEvent::ModifiersChanged(CTRL));
+        assert_eq!(
+            presses(&events)[1],
+            (PhysicalKey::Code(KeyCode::KeyS), None)
+        );
+    }
+    #[test]
+    fn sgr_mouse() {
+        let mut parser = InputParser::new();
+        assert_eq!(
+            parser.feed(b"\x1b[<0;5;3M"),
+            vec![
+                InputEvent::CursorEntered,
+                InputEvent::MouseMoved(4.5, 2.5),
+                InputEvent::MousePressed(WinitMouseButton::Left),
+            ]
+        );
+        assert_eq!(
+            parser.feed(b"\x1b[<2;6;3m"),
+            vec![
+                InputEvent::MouseMoved(5.5, 2.5),
+                InputEvent::MouseReleased(WinitMouseButton::Right),
+            ]
+        );
+        // a movement, then the wheel going down
+        assert_eq!(
+            parser.feed(b"\x1b[<35;1;1M\x1b[<65;1;1M"),
+            vec![
+                InputEvent::MouseMoved(0.5, 0.5),
+                InputEvent::MouseMoved(0.5, 0.5),
+                InputEvent::WheelLines(0., -1.),
+            ]
+        );
+    }
+    #[test]
+    fn sequences_can_be_split() {
+        let mut parser = InputParser::new();
+        assert!(parser.feed(b"\x1b[1;").is_empty());
+        assert!(parser.has_pending());
+        let events = parser.feed(b"2A");
+        assert_eq!(presses(&events)[0].0, PhysicalKey::Code(KeyCode::ArrowUp));
+        assert!(!parser.has_pending());
+        // UTF-8 too
+        let e = "é".as_bytes();
+        assert!(parser.feed(&e[..1]).is_empty());
+        assert_eq!(presses(&parser.feed(&e[1..]))[0].1.as_deref(), Some("é"));
+    }
+    #[test]
+    fn lone_escapes_wait_for_the_flush() {
+        let mut parser = InputParser::new();
+        assert!(parser.feed(b"\x1b").is_empty());
+        assert!(parser.has_pending());
+        let events = parser.flush();
+        assert_eq!(
+            presses(&events),
+            vec![(PhysicalKey::Code(KeyCode::Escape), None)]
+        );
+        assert!(!parser.has_pending());
+        // escape then a key is alt + the key, which types nothing
+        let events = parser.feed(b"\x1bx");
+        assert!(matches!(
+            events[0],
+            InputEvent::ModifiersChanged(Modifiers { alt: true, .. })
+        ));
+        assert_eq!(presses(&events)[0].1, None);
+    }
+    #[test]
+    fn colors_are_rounded_to_the_palette() {
+        // the cube
+        assert_eq!(to_256((255, 0, 0)), 196);
+        assert_eq!(to_256((0, 0, 0)), 16);
+        assert_eq!(to_256((100, 130, 180)), 67);
+        // the grays
+        assert_eq!(to_256((128, 128, 128)), 244);
+        assert_eq!(to_256((240, 238, 238)), 255);
+        for (i, color) in VGA_PALETTE.iter().enumerate() {
+            assert_eq!(to_16((color.0, color.1, color.2)) as usize, i);
+        }
+        assert_eq!(to_16((250, 80, 70)), 9);
+        // the bright colors have their own codes
+        let red = (VGA_PALETTE[1].0, VGA_PALETTE[1].1, VGA_PALETTE[1].2);
+        let bright_red = (VGA_PALETTE[9].0, VGA_PALETTE[9].1, VGA_PALETTE[9].2);
+        assert_eq!(ColorMode::Colors16.sgr(red, true), "41");
+        assert_eq!(ColorMode::Colors16.sgr(bright_red, false), "91");
+        assert_eq!(ColorMode::Colors256.sgr((255, 0, 0), false), "38;5;196");
+        assert_eq!(ColorMode::TrueColor.sgr((1, 2, 3), true), "48;2;1;2;3");
+    }
+    #[test]
+    fn only_the_changes_are_sent() {
+        let mut window = Window::headless(Config::default());
+        let mut renderer = AnsiRenderer::new(ColorMode::TrueColor);
+        let (width, height) = window.grid_size();
+        let first = renderer.render(&window);
+        assert!(first.starts_with("\x1b[0m\x1b[2J"));
+        assert_eq!(visible(&first).len(), (width * height) as usize);
+        assert_eq!(renderer.render(&window), "");
+        // two cells next to each other only need one cursor move
+        window.print_at(3, 2, "@!", None, None);
+        let out = renderer.render(&window);
+        assert!(out.starts_with("\x1b[3;4H"));
+        assert_eq!(out.matches('H').count(), 1);
+        assert_eq!(visible(&out), "@!");
+        assert_eq!(renderer.render(&window), "");
+        // & everything is sent again after invalidate
+        renderer.invalidate();
+        assert_eq!(
+            visible(&renderer.render(&window)).len(),
+            (width * height) as usize
+        );
+    }
+}
@@ -1,7 +1,10 @@
 //! /!\ Nobody should use this module; it is terrible /!\
 use std::collections::HashMap;
 
-use crate::{Col, NiceKeyboard, Window};
+use crate::{
+    input_map::{Binding, InputMap},
+    Col, NiceKeyboard, Window,
+};
 
 /// create an ui context, represented by an empty UIBox, which has a BoxPlacementStyle of Full
 pub fn ui_context(start: (i32, i32), end: (i32, i32), data: UIData) -> UIRoot {
@@ -538,6 +541,29 @@ impl Default for UIConfig {
         } // todo!()
     }
 }
+impl UIConfig {
+    /// takes the keys from an input map's `ui_select`, `ui_down`, `ui_up` & `ui_cancel` actions (their first key without modifiers)
+    /// an action without such a key leaves that key unset
+    pub fn from_input_map(map: &InputMap) -> Self {
+        let key = |action: &str| {
+            map.bindings(action)
+                .iter()
+                .find_map(|binding| match binding {
+                    Binding::Key(chord) if chord.modifiers == Default::default() => {
+                        Some(crate::TheKeyTypeFromWinit::Code(chord.key))
+                    }
+                    _ => None,
+                })
+        };
+        Self {
+            key_select: key("ui_select"),
+            key_down: key("ui_down"),
+            key_up: key("ui_up"),
+            key_exit: key("ui_cancel"),
+            ..Default::default()
+        }
+    }
+}
 /// is implemented for () to act as an empty element which does nothing
 impl UI for () {
     fn render_and_process(
@@ -551,3 +577,25 @@ impl UI for () {
     {
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TheKeyCodeTypeFromWinit as KeyCode, TheKeyTypeFromWinit as Key};
+
+    #[test]
+    fn ui_keys_come_from_the_input_map() {
+        let mut map = InputMap::vi_keys();
+        // the first key without modifiers is the one taken
+        map.clear_action("ui_select");
+        map.bind_str("ui_select", "Ctrl+Enter").unwrap();
+        map.bind_str("ui_select", "MouseLeft").unwrap();
+        map.bind_str("ui_select", "Space").unwrap();
+        map.clear_action("ui_cancel");
+        let config = UIConfig::from_input_map(&map);
+        assert_eq!(config.key_select, Some(Key::Code(KeyCode::Space)));
+        assert_eq!(config.key_up, Some(Key::Code(KeyCode::ArrowUp)));
+        assert_eq!(config.key_down, Some(Key::Code(KeyCode::ArrowDown)));
+        assert_eq!(config.key_exit, None);
+        assert!(config.uses_mouse);
+    }
+}